@@ -1,12 +1,94 @@
 use clap::{Parser, Subcommand, ValueEnum};
-use router::{Fabric, NetInternal, RouteNet, SimpleSolver, SimpleSteinerSolver, SteinerSolver};
+use router::{Fabric, Heuristic, NetInternal, NetOrdering, RouteNet, RouteTreeSolver, StagnationResponse, SimpleSolver, SimpleSteinerSolver, SteinerSolver};
 
 #[derive(ValueEnum, Clone, Debug)]
 pub enum SolverType {
     Simple,
     Steiner,
     SimpleSteiner,
+    /// Grows a route tree sink by sink from all of its nodes
+    RouteTree,
 }
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum HeuristicType {
+    /// Plain Dijkstra
+    None,
+    /// A* with a Manhattan distance lower bound
+    Manhattan,
+    /// A* guided by a precomputed lookahead table
+    Lookahead,
+}
+
+impl From<HeuristicType> for Heuristic {
+    fn from(value: HeuristicType) -> Self {
+        match value {
+            HeuristicType::None => Self::None,
+            HeuristicType::Manhattan => Self::Manhattan,
+            HeuristicType::Lookahead => Self::Lookahead,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CostModelType {
+    /// VPR's negotiated congestion costs with a growing present factor
+    Vpr,
+    /// `(1 + historic) * (1 + usage)`
+    Legacy,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum NetOrderingType {
+    /// Order of the net-list
+    AsGiven,
+    /// Nets with more sinks first
+    Fanout,
+    /// Nets with a larger bounding box first
+    BoundingBox,
+    /// Most critical nets of the last timing analysis first
+    Criticality,
+    /// Nets using the most overused nodes of the previous iteration first
+    Congestion,
+    /// Random order drawn from `--net-order-seed`
+    Random,
+}
+
+impl NetOrderingType {
+    pub const fn to_net_ordering(self, seed: u64) -> NetOrdering {
+        match self {
+            Self::AsGiven => NetOrdering::AsGiven,
+            Self::Fanout => NetOrdering::Fanout,
+            Self::BoundingBox => NetOrdering::BoundingBox,
+            Self::Criticality => NetOrdering::Criticality,
+            Self::Congestion => NetOrdering::Congestion,
+            Self::Random => NetOrdering::Random { seed },
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StagnationResponseType {
+    /// Re-runs the pre-processing of the solver
+    PreProcess,
+    /// Multiplies the historic costs of overused nodes by `--historic-boost`
+    HistoricBoost,
+    /// Routes the next iteration in a random order drawn from `--net-order-seed`
+    RandomReorder,
+    /// Grows the bounding box of every net by one tile, requires `--bb-margin`
+    WidenBoundingBoxes,
+}
+
+impl StagnationResponseType {
+    pub const fn to_response(self, historic_boost: f32, seed: u64) -> StagnationResponse {
+        match self {
+            Self::PreProcess => StagnationResponse::PreProcess,
+            Self::HistoricBoost => StagnationResponse::HistoricBoost(historic_boost),
+            Self::RandomReorder => StagnationResponse::RandomReorder { seed },
+            Self::WidenBoundingBoxes => StagnationResponse::WidenBoundingBoxes(1),
+        }
+    }
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum LoggerType {
     No,
@@ -24,9 +106,75 @@ pub struct CreateTestArgs {
     pub destinations: usize,
     #[arg(short, long, default_value_t = 0.2)]
     pub percentage: f32,
+    /// Seed of the generator, a random seed is chosen if omitted
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    /// Maximum Manhattan distance in tiles between a signal and its sinks
+    #[arg(long, conflicts_with = "rent_exponent")]
+    pub radius: Option<u8>,
+    /// Rent's-rule style locality, larger exponents keep more connections local
+    #[arg(long)]
+    pub rent_exponent: Option<f32>,
+    /// Draws the fanout of each net from a realistic distribution capped at `destinations`
+    #[arg(long, default_value_t = false)]
+    pub mixed_fanout: bool,
+    /// Number of nets driven by VCC
+    #[arg(long, default_value_t = 0)]
+    pub vcc_nets: usize,
+    /// Number of nets driven by GND
+    #[arg(long, default_value_t = 0)]
+    pub gnd_nets: usize,
 }
 
+#[derive(Parser, Debug)]
+pub struct CreateSequentialArgs {
+    /// The generated `net-list.json`
+    #[arg(short, long)]
+    pub output: String,
+    /// The generated FASM configuring the registers and LUTs, used as `--ffs` when routing
+    #[arg(short, long)]
+    pub ffs: String,
+    #[arg(short, long)]
+    pub graph: String,
+    /// Number of register-to-register chains
+    #[arg(short, long, default_value_t = 4)]
+    pub chains: usize,
+    /// Combinational LUTs between two registers
+    #[arg(short, long, default_value_t = 2)]
+    pub depth: usize,
+    /// Seed of the generator, a random seed is chosen if omitted
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+    /// Maximum Manhattan distance in tiles between two consecutive LUTs of a chain
+    #[arg(long)]
+    pub radius: Option<u8>,
+}
 
+#[derive(Parser, Debug)]
+pub struct DiffArgs {
+    /// The routed net-list used as reference
+    #[arg(long)]
+    pub old: String,
+    /// The routed net-list that is compared against the reference
+    #[arg(long)]
+    pub new: String,
+    /// Writes the full difference as JSON
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct AnalyzeArgs {
+    #[arg(short, long)]
+    pub graph: String,
+    #[arg(short, long)]
+    pub bel: String,
+    #[arg(short, long)]
+    pub net_list: String,
+    /// Writes the full report as JSON
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
 
 #[derive(Parser, Debug)]
 pub struct RouteArgs {
@@ -46,10 +194,110 @@ pub struct RouteArgs {
     pub max_iterations: usize,
     #[arg(short, long)]
     pub ffs: Option<String>,
-    #[arg(short, long)]
+    /// `-t` belongs to the timings file, `--timing-driven` has no short flag so both stay unique
+    #[arg(short = 't', long)]
     pub timings: String,
-    #[arg(short, long, default_value_t = false)]
+    #[arg(long, default_value_t = false)]
     pub timing_driven: bool,
+    /// Heuristic guiding the path searches of the solver
+    #[arg(long, value_enum, default_value_t = HeuristicType::None)]
+    pub heuristic: HeuristicType,
+    /// Lookahead table created by the `lookahead` command. Computed on the fly if the
+    /// lookahead heuristic is used without a table.
+    #[arg(long)]
+    pub lookahead: Option<String>,
+    /// Restricts the searches of a net to the bounding box of its pins grown by this many tiles
+    #[arg(long)]
+    pub bb_margin: Option<u8>,
+    /// Only re-routes nets that use an overused node in the following iteration
+    #[arg(long, default_value_t = false)]
+    pub incremental: bool,
+    /// Routes batches of nets with disjoint bounding boxes in parallel, best combined with `--bb-margin`
+    #[arg(long, default_value_t = false)]
+    pub parallel_nets: bool,
+    /// Order in which the nets are routed in every iteration
+    #[arg(long, value_enum, default_value_t = NetOrderingType::AsGiven)]
+    pub net_ordering: NetOrderingType,
+    /// Seed of the random net ordering
+    #[arg(long, default_value_t = 0)]
+    pub net_order_seed: u64,
+    #[command(flatten)]
+    pub convergence: ConvergenceArgs,
+    #[command(flatten)]
+    pub constants: ConstantArgs,
+    #[command(flatten)]
+    pub failure: FailureArgs,
+    /// Lets connections route through free LUTs configured as buffers at this base cost
+    /// (10 if the flag is given without a value)
+    #[arg(long, num_args = 0..=1, default_missing_value = "10")]
+    pub lut_route_through: Option<f32>,
+    #[arg(long, value_enum, default_value_t = CostModelType::Vpr)]
+    pub cost_model: CostModelType,
+    /// Present congestion factor of the first iteration (VPR cost model)
+    #[arg(long, default_value_t = 0.5)]
+    pub pres_fac_initial: f32,
+    /// Growth of the present congestion factor per iteration (VPR cost model)
+    #[arg(long, default_value_t = 1.3)]
+    pub pres_fac_growth: f32,
+    /// Upper limit of the present congestion factor (VPR cost model)
+    #[arg(long, default_value_t = 1000.0)]
+    pub pres_fac_max: f32,
+}
+
+/// Handling of the `VCC0` and `GND0` nets of the `route` command
+#[derive(Parser, Debug)]
+pub struct ConstantArgs {
+    /// Folds constant LUT inputs into the INITs of the `--ffs` file instead of routing them
+    #[arg(long, default_value_t = false)]
+    pub fold_constants: bool,
+    /// Tiles around a sink searched for a constant driver when its `VCC0`/`GND0` cannot reach it
+    #[arg(long, default_value_t = 2)]
+    pub constant_radius: u8,
+}
+
+/// Handling of unroutable connections and failed runs of the `route` command
+#[derive(Parser, Debug)]
+pub struct FailureArgs {
+    /// Excludes connections without any path to their sink instead of failing, they are listed in the result
+    #[arg(long, default_value_t = false)]
+    pub skip_unroutable: bool,
+    /// Writes the routing with the fewest conflicts to this file if the routing fails (`.json` or `.fasm`)
+    #[arg(long)]
+    pub partial_output: Option<String>,
+}
+
+/// Stop criteria and stagnation handling of the `route` command
+#[derive(Parser, Debug)]
+pub struct ConvergenceArgs {
+    /// Interrupts the routing after this many seconds, also in the middle of an iteration
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+    /// Stops the routing when the conflicts did not improve for this many iterations
+    #[arg(long)]
+    pub no_improvement: Option<usize>,
+    /// Stops the routing when the conflict trend does not reach zero within `--max-iterations`
+    #[arg(long, default_value_t = false)]
+    pub predict_unroutable: bool,
+    /// Iterations without improvement after which the routing counts as stagnated
+    #[arg(long, default_value_t = 200)]
+    pub stagnation_window: usize,
+    /// Responses applied when the routing stagnates
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "pre-process")]
+    pub on_stagnation: Vec<StagnationResponseType>,
+    /// Factor of the historic-boost stagnation response, at least 1
+    #[arg(long, default_value_t = 2.0)]
+    pub historic_boost: f32,
+}
+
+#[derive(Parser, Debug)]
+pub struct LookaheadArgs {
+    #[arg(short, long)]
+    pub graph: String,
+    /// Timing model used for the base costs, must match the one used for routing
+    #[arg(short, long)]
+    pub timings: Option<String>,
+    #[arg(short, long)]
+    pub output: String,
 }
 
 // --- CLI Structure ---
@@ -65,14 +313,23 @@ pub struct Cli {
 pub enum Commands {
     /// Creates a test `net-list.json`
     CreateTest(CreateTestArgs),
+    /// Creates a test `net-list.json` of register-to-register chains and the matching FF FASM
+    CreateSequential(CreateSequentialArgs),
     /// Runs the router
-    Route(RouteArgs),
+    Route(Box<RouteArgs>),
+    /// Compares two routed `net-list.json` files
+    Diff(DiffArgs),
+    /// Precomputes the lookahead table of a fabric
+    Lookahead(LookaheadArgs),
+    /// Reports statistics and the estimated routability of a `net-list.json`
+    Analyze(AnalyzeArgs),
 }
 
 pub enum Solver {
     Simple(SimpleSolver),
     SimpleSteiner(SimpleSteinerSolver),
     Steiner(SteinerSolver),
+    RouteTree(RouteTreeSolver),
 }
 
 impl RouteNet for Solver {
@@ -81,6 +338,25 @@ impl RouteNet for Solver {
             Self::Simple(simple_solver) => simple_solver.solve(graph, routing),
             Self::SimpleSteiner(simple_steiner_solver) => simple_steiner_solver.solve(graph, routing),
             Self::Steiner(steiner_solver) => steiner_solver.solve(graph, routing),
+            Self::RouteTree(route_tree_solver) => route_tree_solver.solve(graph, routing),
+        }
+    }
+
+    fn solve_shared(&self, fabric: &Fabric, routing: &mut NetInternal) -> Option<router::FabricResult<()>> {
+        match self {
+            Self::Simple(simple_solver) => simple_solver.solve_shared(fabric, routing),
+            Self::SimpleSteiner(simple_steiner_solver) => simple_steiner_solver.solve_shared(fabric, routing),
+            Self::Steiner(steiner_solver) => steiner_solver.solve_shared(fabric, routing),
+            Self::RouteTree(route_tree_solver) => route_tree_solver.solve_shared(fabric, routing),
+        }
+    }
+
+    fn commit(&self, fabric: &mut Fabric, routing: &mut NetInternal) -> router::FabricResult<()> {
+        match self {
+            Self::Simple(simple_solver) => simple_solver.commit(fabric, routing),
+            Self::SimpleSteiner(simple_steiner_solver) => simple_steiner_solver.commit(fabric, routing),
+            Self::Steiner(steiner_solver) => steiner_solver.commit(fabric, routing),
+            Self::RouteTree(route_tree_solver) => route_tree_solver.commit(fabric, routing),
         }
     }
 
@@ -89,6 +365,7 @@ impl RouteNet for Solver {
             Self::Simple(simple_solver) => simple_solver.pre_process(graph, route_plan),
             Self::SimpleSteiner(simple_steiner_solver) => simple_steiner_solver.pre_process(graph, route_plan),
             Self::Steiner(steiner_solver) => steiner_solver.pre_process(graph, route_plan),
+            Self::RouteTree(route_tree_solver) => route_tree_solver.pre_process(graph, route_plan),
         }
     }
 
@@ -97,6 +374,29 @@ impl RouteNet for Solver {
             Self::Simple(simple_solver) => simple_solver.identifier(),
             Self::SimpleSteiner(simple_steiner_solver) => simple_steiner_solver.identifier(),
             Self::Steiner(steiner_solver) => steiner_solver.identifier(),
+            Self::RouteTree(route_tree_solver) => route_tree_solver.identifier(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_route_short_flags() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from([
+            "router-cli", "route", "-g", "pips.txt", "-b", "bel.txt", "-n", "net-list.json", "-o", "out.fasm", "-t",
+            "timing_model.json", "--timing-driven",
+        ])
+        .unwrap();
+        let Commands::Route(args) = cli.command else {
+            panic!("expected the route command");
+        };
+        assert_eq!(args.timings, "timing_model.json");
+        assert!(args.timing_driven);
+    }
+}
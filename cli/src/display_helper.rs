@@ -1,8 +1,20 @@
-use router::{CongestionReportExtern, IterationResult, RouteNet, Swap};
+use router::{CongestionReportExtern, IterationResult, NetListDiff, RoutabilityReport, RouteNet, SequentialConfig, Swap, TestConfig, UnroutableSinkExternal};
 
-use crate::cli::{CreateTestArgs, RouteArgs};
+use crate::cli::{CreateSequentialArgs, CreateTestArgs, RouteArgs};
+
+pub fn display_results(results: &[IterationResult], swaps: &[Swap], unroutable: &[UnroutableSinkExternal]) {
+    if !unroutable.is_empty() {
+        println!("\nUnroutable Connections Skipped:");
+        println!("{:-<110}", "");
+        println!("{:<20} | {:<20} | {:<60}", "Signal", "Sink", "Reason");
+        println!("{:-<110}", "");
+
+        for connection in unroutable {
+            println!("{:<20} | {:<20} | {:<60}", connection.signal.id, connection.sink.id, connection.reason);
+        }
+        println!("{:-<110}\n", "");
+    }
 
-pub fn display_results(results: &[IterationResult], swaps: &[Swap]) {
     if !swaps.is_empty() {
         println!("\nInput Swaps Performed:");
         println!("{:-<110}", "");
@@ -55,6 +67,9 @@ pub fn display_metadata_route<T: RouteNet>(config: &RouteArgs, solver: &T) {
     println!("{:<20}: {}", "Netlist File", config.net_list);
     println!("{:<20}: {}", "Max Iterations", config.max_iterations);
     println!("{:<20}: {}", "History Factor", config.hist_factor);
+    println!("{:<20}: {:?}", "Heuristic", config.heuristic);
+    println!("{:<20}: {:?}", "Cost Model", config.cost_model);
+    println!("{:<20}: {:?}", "Net Ordering", config.net_ordering);
     println!(
         "{:<20}: {}",
         "Flip-Flop file",
@@ -64,14 +79,33 @@ pub fn display_metadata_route<T: RouteNet>(config: &RouteArgs, solver: &T) {
     println!("{:<20}: {}", "Timing Driven", if config.timing_driven { "Yes" } else { "No" });
     println!("{:=<60}\n", "");
 }
-pub fn display_run_create_test(config: &CreateTestArgs) {
+pub fn display_run_create_test(args: &CreateTestArgs, config: &TestConfig) {
     println!("{:=<60}", "");
     println!(" FPGA CREATE TEST CONFIGURATION");
     println!("{:-<60}", "");
-    println!("{:<20}: {}", "Graph File", config.graph);
-    println!("{:<20}: {}", "Output File", config.output);
+    println!("{:<20}: {}", "Graph File", args.graph);
+    println!("{:<20}: {}", "Output File", args.output);
+    println!("{:<20}: {}", "Seed", config.seed);
     println!("{:<20}: {}", "LUT Percentage", config.percentage);
-    println!("{:<20}: {}", "LUT Destinations", config.destinations);
+    println!("{:<20}: {:?}", "LUT Destinations", config.fanout);
+    println!("{:<20}: {:?}", "Locality", config.locality);
+    println!("{:<20}: {}", "VCC Nets", config.vcc_nets);
+    println!("{:<20}: {}", "GND Nets", config.gnd_nets);
+
+    println!("{:=<60}\n", "");
+}
+
+pub fn display_run_create_sequential(args: &CreateSequentialArgs, config: &SequentialConfig) {
+    println!("{:=<60}", "");
+    println!(" FPGA CREATE SEQUENTIAL TEST CONFIGURATION");
+    println!("{:-<60}", "");
+    println!("{:<20}: {}", "Graph File", args.graph);
+    println!("{:<20}: {}", "Output File", args.output);
+    println!("{:<20}: {}", "FF FASM File", args.ffs);
+    println!("{:<20}: {}", "Seed", config.seed);
+    println!("{:<20}: {}", "Chains", config.chains);
+    println!("{:<20}: {}", "Logic Depth", config.logic_depth);
+    println!("{:<20}: {:?}", "Locality", config.locality);
 
     println!("{:=<60}\n", "");
 }
@@ -108,7 +142,7 @@ pub fn display_failed_routing(congestion_report: &CongestionReportExtern, iterat
 
     // Sort by number of nets sharing the same resource
     let mut congestion_list: Vec<_> = congestion_report.congestion.iter().collect();
-    congestion_list.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+    congestion_list.sort_by_key(|(_, nets)| std::cmp::Reverse(nets.len()));
 
     for (resource, nets) in congestion_list.iter().take(10) {
         println!("{:<25} | {} nets: {:?}", resource, nets.len(), nets);
@@ -129,3 +163,100 @@ pub fn display_failed_routing(congestion_report: &CongestionReportExtern, iterat
     println!("\n{:=^80}", " END OF REPORT ");
     println!("Hint: If conflicts are high, try increasing 'hist_factor' or 'max_iterations'.");
 }
+
+pub fn display_diff(diff: &NetListDiff) {
+    println!("{:=<80}", "");
+    println!(" NET-LIST DIFFERENCE");
+    println!("{:-<80}", "");
+    println!("{:<20}: {}", "Added Nets", diff.added_nets.len());
+    println!("{:<20}: {}", "Removed Nets", diff.removed_nets.len());
+    println!("{:<20}: {}", "Changed Nets", diff.changed_nets.len());
+    println!(
+        "{:<20}: {} -> {} ({:+})",
+        "Wire Length",
+        diff.old_wire_length,
+        diff.new_wire_length,
+        diff.wire_length_delta()
+    );
+    println!("{:<20}: {} -> {} ({:+})", "Hops", diff.old_hops, diff.new_hops, diff.hops_delta());
+    println!("{:=<80}\n", "");
+
+    for signal in &diff.added_nets {
+        println!("+ {signal}");
+    }
+    for signal in &diff.removed_nets {
+        println!("- {signal}");
+    }
+    if diff.changed_nets.is_empty() {
+        return;
+    }
+
+    println!("\n{:<25} | {:<6} | {:<6} | {:<8} | {:<8} | {:<8}", "Net", "+Sinks", "-Sinks", "Paths", "Wire", "Hops");
+    println!("{:-<80}", "");
+    for net in &diff.changed_nets {
+        println!(
+            "{:<25} | {:<6} | {:<6} | {:<8} | {:<+8} | {:<+8}",
+            net.signal.to_string(),
+            net.added_sinks.len(),
+            net.removed_sinks.len(),
+            net.changed_paths.len(),
+            net.wire_length_delta,
+            net.hops_delta
+        );
+    }
+    println!("{:-<80}", "");
+}
+
+pub fn display_routability(report: &RoutabilityReport) {
+    println!("{:=<80}", "");
+    println!(" NET-LIST ROUTABILITY REPORT");
+    println!("{:-<80}", "");
+    println!("{:<25}: {}", "Nets", report.net_count);
+    println!("{:<25}: {}", "Connections", report.connection_count);
+    println!("{:<25}: {}", "Constant Nets", report.constant_nets);
+    println!("{:<25}: {}", "Total HPWL", report.total_half_perimeter);
+
+    println!("\n### Fanout Histogram");
+    println!("{:-<80}", "");
+    for (fanout, count) in &report.fanout_histogram {
+        println!("{fanout:>6} | {count}");
+    }
+
+    println!("\n### Largest Nets (Net -> HPWL)");
+    println!("{:-<80}", "");
+    let mut nets: Vec<_> = report.nets.iter().collect();
+    nets.sort_by_key(|net| std::cmp::Reverse(net.half_perimeter));
+    for net in nets.iter().take(10) {
+        println!("{:<25} | HPWL: {:<4} | Fanout: {}", net.signal.to_string(), net.half_perimeter, net.fanout);
+    }
+
+    println!("\n### LUT Input Demand (Tile -> Demand / Supply)");
+    println!("{:-<80}", "");
+    for usage in &report.tile_pin_usage {
+        println!("{:<10} | {:>4} / {:<4}", usage.tile.to_string(), usage.demand, usage.supply);
+    }
+
+    println!("\n### Most Utilized Cut-Lines (Cut -> Demand / Supply)");
+    println!("{:-<80}", "");
+    let mut cut_lines: Vec<_> = report.cut_lines.iter().collect();
+    cut_lines.sort_by(|a, b| b.utilization().total_cmp(&a.utilization()));
+    for cut in cut_lines.iter().take(10) {
+        println!(
+            "{:<10} {:>3} | {:>5} / {:<5}",
+            format!("{:?}", cut.direction),
+            cut.position,
+            cut.demand,
+            cut.supply
+        );
+    }
+
+    println!("\n{:=<80}", "");
+    if report.is_likely_unroutable() {
+        println!("The design is likely unroutable:");
+        for warning in &report.warnings {
+            println!("  - {warning}");
+        }
+    } else {
+        println!("No routability problems detected.");
+    }
+}
@@ -11,26 +11,37 @@ use std::{
     fs::{self, File},
     io::BufReader,
     path::Path,
+    time::Duration,
 };
 
 use anyhow::{Context, Result, anyhow};
 use clap::Parser;
 use router::{
-    Fabric, FabricError, FabricGraph, FabricResult, NetListExternal, RoutingConfig, RoutingConfigBuilder, SimpleSolver,
-    SimpleSteinerSolver, SlackReport, SteinerSolver, TileManager, TimingAnalysis, create_fasm, create_test, 
-    route, route_timing_driven,
+    Fabric, FabricError, FabricGraph, FabricResult, Fanout, LegacyCostModel, Locality, LookaheadTable, NetListExternal, PartialRouting, RipUp, RouteTreeSolver, RoutingConfig, RoutingConfigBuilder,
+    SequentialConfig, SimpleSolver, SimpleSteinerSolver, SlackReport, Stagnation, SteinerSolver, StopCriteria, TestConfig, TileManager, TimingAnalysis, VprCostModel,
+    analyze_routability, create_fasm, generate_sequential_test, generate_test, route, route_timing_driven, strip_lut_inits,
 };
 
 use crate::{
-    cli::{Cli, Commands, CreateTestArgs, Solver, SolverType},
-    display_helper::{display_failed_routing, display_metadata_route, display_results, display_run_create_test},
+    cli::{
+        AnalyzeArgs, Cli, Commands, CostModelType, CreateSequentialArgs, CreateTestArgs, DiffArgs, HeuristicType, LookaheadArgs, Solver,
+        SolverType,
+    },
+    display_helper::{
+        display_diff, display_failed_routing, display_routability, display_metadata_route, display_results,
+        display_run_create_sequential, display_run_create_test,
+    },
     logger::TerminalLogger,
 };
 
 fn main() -> Result<()> {
     match Cli::parse().command {
         Commands::CreateTest(args) => command_create_test(&args),
+        Commands::CreateSequential(args) => command_create_sequential(&args),
         Commands::Route(args) => command_route(&args),
+        Commands::Diff(args) => command_diff(&args),
+        Commands::Lookahead(args) => command_lookahead(&args),
+        Commands::Analyze(args) => command_analyze(&args),
     }?;
     Ok(())
 }
@@ -38,9 +49,27 @@ fn main() -> Result<()> {
 fn command_create_test(args: &CreateTestArgs) -> Result<()> {
     let graph =
         FabricGraph::from_file(&args.graph, None).with_context(|| format!("Failed to load fabric graph from {}", args.graph))?;
+    let locality = match (args.radius, args.rent_exponent) {
+        (Some(radius), _) => Locality::MaxRadius(radius),
+        (None, Some(exponent)) => Locality::Rent { exponent },
+        (None, None) => Locality::Uniform,
+    };
+    let fanout = if args.mixed_fanout {
+        Fanout::mixed(args.destinations)
+    } else {
+        Fanout::Fixed(args.destinations)
+    };
+    let config = TestConfig {
+        seed: args.seed.unwrap_or_else(rand::random),
+        percentage: args.percentage,
+        fanout,
+        locality,
+        vcc_nets: args.vcc_nets,
+        gnd_nets: args.gnd_nets,
+    };
     let _ = clearscreen::clear();
-    display_run_create_test(args);
-    let net_list = create_test(&graph, args.percentage, args.destinations).with_context(|| "Failed to create test File")?;
+    display_run_create_test(args, &config);
+    let net_list = generate_test(&graph, &config).with_context(|| "Failed to create test File")?;
 
     let pretty = serde_json::to_string_pretty(&net_list)
         .with_context(|| "Failed to serialize the net-list into a readable JSON format")?;
@@ -49,8 +78,88 @@ fn command_create_test(args: &CreateTestArgs) -> Result<()> {
     Ok(())
 }
 
+fn command_create_sequential(args: &CreateSequentialArgs) -> Result<()> {
+    let graph =
+        FabricGraph::from_file(&args.graph, None).with_context(|| format!("Failed to load fabric graph from {}", args.graph))?;
+    let config = SequentialConfig {
+        seed: args.seed.unwrap_or_else(rand::random),
+        chains: args.chains,
+        logic_depth: args.depth,
+        locality: args.radius.map_or(Locality::Uniform, Locality::MaxRadius),
+    };
+    let _ = clearscreen::clear();
+    display_run_create_sequential(args, &config);
+    let test = generate_sequential_test(&graph, &config).with_context(|| "Failed to create sequential test")?;
+
+    let pretty = serde_json::to_string_pretty(&test.net_list)
+        .with_context(|| "Failed to serialize the net-list into a readable JSON format")?;
+    fs::write(&args.output, pretty).with_context(|| format!("Failed to write the generated test net-list to {}", args.output))?;
+    fs::write(&args.ffs, test.fasm()).with_context(|| format!("Failed to write the generated FF FASM to {}", args.ffs))?;
+    println!("Created sequential test with {} registers.", test.registers.len());
+    Ok(())
+}
+
+fn command_lookahead(args: &LookaheadArgs) -> Result<()> {
+    let timing_model = args
+        .timings
+        .as_ref()
+        .map(|path| -> Result<router::TimingModel> {
+            let file = File::open(path).with_context(|| format!("Failed to open timings {path}"))?;
+            let sta: Sta = serde_json::from_reader(BufReader::new(file))?;
+            Ok(router_timing_model(&sta.timing_model))
+        })
+        .transpose()?;
+    let graph = FabricGraph::from_file(&args.graph, timing_model)
+        .with_context(|| format!("Failed to load fabric graph from {}", args.graph))?;
+    let table = LookaheadTable::compute(&graph);
+    table
+        .to_file(&args.output)
+        .with_context(|| format!("Failed to write the lookahead table to {}", args.output))?;
+    println!("Created lookahead table for a {}x{} fabric.", table.width, table.height);
+    Ok(())
+}
+
+fn command_diff(args: &DiffArgs) -> Result<()> {
+    let old = NetListExternal::from_file(&args.old).with_context(|| format!("Failed to load net-list {}", args.old))?;
+    let new = NetListExternal::from_file(&args.new).with_context(|| format!("Failed to load net-list {}", args.new))?;
+    let diff = old.diff(&new);
+    display_diff(&diff);
+    if let Some(output) = &args.output {
+        let pretty = serde_json::to_string_pretty(&diff).with_context(|| "Failed to serialize the net-list difference")?;
+        fs::write(output, pretty).with_context(|| format!("Failed to write the net-list difference to {output}"))?;
+    }
+    Ok(())
+}
+
+fn command_analyze(args: &AnalyzeArgs) -> Result<()> {
+    let graph =
+        FabricGraph::from_file(&args.graph, None).with_context(|| format!("Failed to load fabric graph from {}", args.graph))?;
+    let tile_manager = TileManager::from_file(&args.bel).with_context(|| format!("Failed to load bels from {}", args.bel))?;
+    let net_list =
+        NetListExternal::from_file(&args.net_list).with_context(|| format!("Failed to load net-list {}", args.net_list))?;
+    let fabric = Fabric::new(graph, tile_manager);
+    let report = analyze_routability(&fabric, &net_list);
+    display_routability(&report);
+    if let Some(output) = &args.output {
+        let pretty = serde_json::to_string_pretty(&report).with_context(|| "Failed to serialize the routability report")?;
+        fs::write(output, pretty).with_context(|| format!("Failed to write the routability report to {output}"))?;
+    }
+    Ok(())
+}
+
 fn command_route(args: &cli::RouteArgs) -> Result<()> {
     let (mut config, sta) = parse_arguments(args)?;
+    let ffs = args.ffs.as_ref().map_or_else(
+        || Ok("# No FFS provided".to_string()),
+        |path| fs::read_to_string(path).context("Error reading FFS file"),
+    )?;
+    // The INITs are emitted by `create_fasm`, permuted to the routed LUT inputs
+    config
+        .fabric
+        .tile_manager
+        .load_inits(&ffs)
+        .with_context(|| "Failed to load the LUT INITs of the FFS file")?;
+    let ffs = strip_lut_inits(&ffs);
 
     let _ = clearscreen::clear();
     display_metadata_route(args, &config.solver);
@@ -65,38 +174,54 @@ fn command_route(args: &cli::RouteArgs) -> Result<()> {
         Err(router::FabricError::RoutingMaxIterationsReached {
             congestion_report,
             iteration_report,
+            best_routing,
         }) => {
             display_failed_routing(&congestion_report, &iteration_report);
+            write_partial_routing(args, best_routing.as_deref(), &config.fabric.tile_manager, &ffs)?;
             return Err(anyhow!("Routing Failed: Maximum iterations reached."));
         }
+        Err(router::FabricError::RoutingStopped {
+            reason,
+            congestion_report,
+            iteration_report,
+            best_routing,
+        }) => {
+            display_failed_routing(&congestion_report, &iteration_report);
+            write_partial_routing(args, best_routing.as_deref(), &config.fabric.tile_manager, &ffs)?;
+            return Err(anyhow!("Routing Failed: Stopped early, {reason}."));
+        }
+        Err(router::FabricError::RoutingInterrupted {
+            reason,
+            best_routing,
+            iteration_report,
+        }) => {
+            write_partial_routing(args, best_routing.as_deref(), &config.fabric.tile_manager, &ffs)?;
+            let best = iteration_report.iter().map(|result| result.conflicts).min().map_or_else(
+                || "no iteration finished".to_string(),
+                |conflicts| format!("the best iteration had {conflicts} conflicts"),
+            );
+            return Err(anyhow!("Routing Failed: Interrupted, {reason}, {best}."));
+        }
         Err(err) => {
             return Err(err).with_context(|| "Routing engine encounterd critical error.");
         }
     };
 
     let swapped_inputs = result.0.swapped_inputs(&config.net_list);
-    display_results(&result.1, &swapped_inputs);
+    display_results(&result.1, &swapped_inputs, &result.0.unroutable);
     let path = Path::new(&args.output);
     let serialized_net_list = match path.extension().and_then(|s| s.to_str()) {
         Some("fasm") => {
-            let ffs = args.ffs.as_ref().map_or_else(
-                || Ok("# No FFS provided".to_string()),
-                |path| fs::read_to_string(path).context("Error reading FFS file"),
-            )?;
             let fasm = create_fasm(&result.0, &config.fabric.tile_manager)
                 .with_context(|| "Failed to generate FASM output from the routed net-list")?;
             format!("{fasm}\n{ffs}")
         }
         Some("json") => {
-            serde_json::to_string_pretty(&config.net_list).with_context(|| "Failed to serialize net-list for FASM generation")?
+            serde_json::to_string_pretty(&result.0).with_context(|| "Failed to serialize the routed net-list")?
         }
         _ => {
             println!("Unknown file extension defaulting to fasm.");
-            let ffs = args.ffs.as_ref().map_or_else(
-                || Ok("# No FFS provided".to_string()),
-                |path| fs::read_to_string(path).context("Error reading FFS file"),
-            )?;
-            let fasm = create_fasm(&config.net_list, &config.fabric.tile_manager)
+            let fasm = create_fasm(&result.0, &config.fabric.tile_manager)
                 .with_context(|| "Failed to generate FASM output from the routed net-list")?;
             format!("{fasm}\n{ffs}")
         }
@@ -105,6 +230,29 @@ fn command_route(args: &cli::RouteArgs) -> Result<()> {
     Ok(())
 }
 
+/// Writes the best routing of a failed run to `--partial-output`, as JSON or as FASM with the conflicts as comments
+fn write_partial_routing(
+    args: &cli::RouteArgs,
+    partial: Option<&PartialRouting>,
+    tile_manager: &TileManager,
+    ffs: &str,
+) -> Result<()> {
+    let (Some(output), Some(partial)) = (&args.failure.partial_output, partial) else {
+        return Ok(());
+    };
+    let serialized = if Path::new(output).extension().and_then(|s| s.to_str()) == Some("json") {
+        serde_json::to_string_pretty(partial).with_context(|| "Failed to serialize the partial routing")?
+    } else {
+        let fasm = partial
+            .to_fasm(tile_manager)
+            .with_context(|| "Failed to generate FASM output from the partial routing")?;
+        format!("{fasm}\n{ffs}")
+    };
+    fs::write(output, serialized).with_context(|| format!("Failed to write the partial routing to {output}"))?;
+    println!("Wrote the routing with {} conflicts to {output}.", partial.conflicts.len());
+    Ok(())
+}
+
 #[derive(Deserialize, Debug)]
 struct Sta {
     timing_model: TimingModel,
@@ -117,7 +265,7 @@ impl TimingAnalysis for Sta {
     fn timing_analysis(&self, fabric: &Fabric, net_list: &router::NetListInternal) -> FabricResult<SlackReport> {
         let ex = net_list.to_external(&fabric.graph);
         let mut fasm = create_fasm(&ex, &fabric.tile_manager)?;
-        let ffs = fs::read_to_string("ffs.fasm").unwrap();
+        let ffs = strip_lut_inits(&fs::read_to_string("ffs.fasm").unwrap());
         fasm.push('\n');
         fasm.push_str(&ffs);
         let graph = self
@@ -200,35 +348,82 @@ fn slack_report_from_timing_analyis(
         worst_slack,
     })
 }
+const fn router_timing_model(timing_model: &TimingModel) -> router::TimingModel {
+    router::TimingModel {
+        lut_delay: timing_model.lut_delay,
+        pip_delay: timing_model.pip_delay,
+        fanout_delay: timing_model.fanout_delay,
+        clock_to_output_delay: timing_model.clock_to_output_delay,
+        clock_tree_delay: timing_model.clock_tree_delay,
+    }
+}
+
 fn parse_arguments(args: &cli::RouteArgs) -> Result<(RoutingConfig<Solver, TerminalLogger>, Sta)> {
     let solver = match args.solver {
         SolverType::Simple => Solver::Simple(SimpleSolver),
         SolverType::Steiner => Solver::Steiner(SteinerSolver),
         SolverType::SimpleSteiner => Solver::SimpleSteiner(SimpleSteinerSolver),
+        SolverType::RouteTree => Solver::RouteTree(RouteTreeSolver),
     };
     let file = File::open(&args.timings)?;
     let reader = BufReader::new(file);
     let mut sta: Sta = serde_json::from_reader(reader)?;
-    let timing_model = &sta.timing_model;
     sta.graph = Some(fpga_timing_analyzer::pips_parser(&args.graph));
 
-    let graph_timing_model = router::TimingModel {
-        lut_delay: timing_model.lut_delay,
-        pip_delay: timing_model.pip_delay,
-        fanout_delay: timing_model.fanout_delay,
-        clock_to_output_delay: timing_model.clock_to_output_delay,
-        clock_tree_delay: timing_model.clock_tree_delay,
-    };
-
-    let graph = FabricGraph::from_file(&args.graph, Some(graph_timing_model))
+    let mut graph = FabricGraph::from_file(&args.graph, Some(router_timing_model(&sta.timing_model)))
         .with_context(|| format!("Router initialization failed: unable to load graph {}", args.graph))?;
+    if let Some(path) = &args.lookahead {
+        let table = LookaheadTable::from_file(path).with_context(|| format!("Failed to load lookahead table {path}"))?;
+        graph
+            .set_lookahead(table)
+            .with_context(|| format!("Lookahead table {path} does not belong to graph {}", args.graph))?;
+    } else if matches!(args.heuristic, HeuristicType::Lookahead) {
+        let table = LookaheadTable::compute(&graph);
+        graph.set_lookahead(table)?;
+    }
     let net_list = NetListExternal::from_file(&args.net_list)
         .with_context(|| format!("Router initialization failed: unable to load net-list {}", args.net_list))?;
 
     let tile_manager = TileManager::from_file(&args.bel)?;
-    let config = RoutingConfigBuilder::default()
+    let mut builder = RoutingConfigBuilder::default();
+    if let Some(margin) = args.bb_margin {
+        builder = builder.bounding_box_margin(margin);
+    }
+    builder = match args.cost_model {
+        CostModelType::Vpr => builder.cost_model(VprCostModel {
+            initial_present_factor: args.pres_fac_initial,
+            present_factor_growth: args.pres_fac_growth,
+            max_present_factor: args.pres_fac_max,
+        }),
+        CostModelType::Legacy => builder.cost_model(LegacyCostModel),
+    };
+    let rip_up = if args.incremental { RipUp::Congested } else { RipUp::All };
+    let config = builder
+        .rip_up(rip_up)
+        .parallel_nets(args.parallel_nets)
+        .fold_constants(args.constants.fold_constants)
+        .constant_search_radius(args.constants.constant_radius)
+        .lut_route_through_cost(args.lut_route_through)
+        .skip_unroutable(args.failure.skip_unroutable)
+        .keep_best_routing(args.failure.partial_output.is_some())
+        .net_ordering(args.net_ordering.to_net_ordering(args.net_order_seed))
+        .stop_criteria(StopCriteria {
+            time_limit: args.convergence.time_limit.map(Duration::from_secs),
+            no_improvement_window: args.convergence.no_improvement,
+            predict_unroutable: args.convergence.predict_unroutable,
+        })
+        .stagnation(Stagnation {
+            window: args.convergence.stagnation_window,
+            responses: args
+                .convergence
+                .on_stagnation
+                .iter()
+                .map(|response| response.to_response(args.convergence.historic_boost, args.net_order_seed))
+                .collect(),
+        })
         .hist_factor(args.hist_factor)
         .max_iterations(args.max_iterations)
+        .heuristic(args.heuristic.into())
         .net_list(net_list)
         .solver(solver)
         .logger(TerminalLogger {})
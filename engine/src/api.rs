@@ -1,25 +1,49 @@
-use rand::seq::SliceRandom;
+use std::sync::Arc;
 
+use crate::constants::{DEFAULT_CONSTANT_SEARCH_RADIUS, fold_constants};
+use crate::cost_model::CostModel;
 use crate::fabric::graph::Fabric;
 use crate::fabric::tile_manager::TileManager;
 use crate::path_finder::{TimingAnalysis, timing_driven_path_finder};
+use crate::route_through::{add_route_throughs, claim_route_throughs, prune_route_throughs};
 use crate::{
-    FabricError, FabricResult, Logging,
-    fabric::graph::{FabricGraph, bucket_luts},
-    fabric::node::NodeId,
+    CancellationToken, FabricError, FabricResult, Heuristic, Logging, NetOrdering, Stagnation, StagnationResponse, StopCriteria,
+    fabric::graph::FabricGraph,
     fasm::net_to_fasm,
-    netlist::{NetExternal, NetInternal, NetListExternal, NetListInternal},
-    path_finder::{Config, path_finder},
+    generator::{Fanout, TestConfig, generate_test},
+    netlist::{NetListExternal, NetListInternal},
+    path_finder::{Config, RipUp, path_finder},
     solver::RouteNet,
     validate,
 };
-use crate::{IterationResult, SimpleLogging, SimpleSolver};
+use crate::{IterationResult, LogInstance, SimpleLogging, SimpleSolver, analyze_routability};
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct RoutingConfig<R: RouteNet, L: Logging> {
     pub fabric: Fabric,
     pub net_list: NetListExternal,
     pub hist_factor: f32,
     pub max_iterations: usize,
+    /// See [`RoutingConfigBuilder::bounding_box_margin`]
+    pub bounding_box_margin: Option<u8>,
+    pub rip_up: RipUp,
+    /// See [`RoutingConfigBuilder::parallel_nets`]
+    pub parallel_nets: bool,
+    pub net_ordering: NetOrdering,
+    pub stop_criteria: StopCriteria,
+    pub stagnation: Stagnation,
+    /// See [`RoutingConfigBuilder::cancellation`]
+    pub cancellation: Option<CancellationToken>,
+    /// See [`RoutingConfigBuilder::fold_constants`]
+    pub fold_constants: bool,
+    /// See [`RoutingConfigBuilder::constant_search_radius`]
+    pub constant_search_radius: u8,
+    /// See [`RoutingConfigBuilder::lut_route_through_cost`]
+    pub lut_route_through_cost: Option<f32>,
+    /// See [`RoutingConfigBuilder::skip_unroutable`]
+    pub skip_unroutable: bool,
+    /// See [`RoutingConfigBuilder::keep_best_routing`]
+    pub keep_best_routing: bool,
     pub solver: R,
     pub logger: L,
 }
@@ -48,17 +72,13 @@ where
     R: RouteNet,
     L: Logging,
 {
-    let net_list_external = &mut config.net_list;
-    let fabric = &mut config.fabric;
-    let mut net_list = NetListInternal::from_external(&fabric.graph, net_list_external)?;
-
-    fabric.check_pathing(&mut net_list)?;
-
-    let router_config = Config::new(config.hist_factor, config.max_iterations);
-    path_finder(&mut net_list, fabric, &router_config, &config.solver, &config.logger).map(|a| {
-        let new_net_list = net_list.to_external(&fabric.graph);
-        (new_net_list, a)
-    })
+    let (mut net_list, router_config) = prepare(config)?;
+    let (net_list_external, fabric) = (&config.net_list, &mut config.fabric);
+    let route_through = config.lut_route_through_cost.is_some();
+    let iterations = path_finder(&mut net_list, fabric, &router_config, &config.solver, &config.logger)
+        .map_err(|err| finish_failed(fabric, net_list_external, route_through, &config.logger, err))?;
+    let new_net_list = finish(fabric, &net_list, net_list_external, route_through, &config.logger)?;
+    Ok((new_net_list, iterations))
 }
 /// Tries to solve a `NetList` and runs the STA to solve for timing constraints
 /// # Errors
@@ -73,98 +93,157 @@ where
     L: Logging,
     T: TimingAnalysis,
 {
-    let net_list_external = &mut config.net_list;
-    let fabric = &mut config.fabric;
-    if let Some(hash) = &net_list_external.hash {
-        if hash != &fabric.graph.calculate_structure_hash() {
+    if let Some(hash) = &config.net_list.hash {
+        if hash != &config.fabric.graph.calculate_structure_hash() {
             eprintln!("Warning: The net-list was not created with this graph.");
         }
     } else {
         eprintln!("Warning: Cannot determine if the net-list was created with this graph. Missing field in net-list.");
     }
-    let mut net_list = NetListInternal::from_external(&fabric.graph, net_list_external)?;
+    let (mut net_list, router_config) = prepare(config)?;
+    let (net_list_external, fabric) = (&config.net_list, &mut config.fabric);
+    let route_through = config.lut_route_through_cost.is_some();
+    let iterations = timing_driven_path_finder(&mut net_list, fabric, &router_config, &config.solver, &config.logger, sta)
+        .map_err(|err| finish_failed(fabric, net_list_external, route_through, &config.logger, err))?;
+    let new_net_list = finish(fabric, &net_list, net_list_external, route_through, &config.logger)?;
+    Ok((new_net_list, iterations))
+}
 
-    fabric.check_pathing(&mut net_list)?;
+/// Maps the net-list of `config` onto its fabric and prepares the fabric for the routing: folds
+/// constants, adds the LUT route-throughs and checks that every sink can be reached.
+/// Returns the internal net-list and the path finder configuration.
+fn prepare<R: RouteNet, L: Logging>(config: &mut RoutingConfig<R, L>) -> FabricResult<(NetListInternal, Config)> {
+    let fabric = &mut config.fabric;
+    let mut net_list = NetListInternal::from_external(&fabric.graph, &config.net_list)?;
+    log_routability_warnings(fabric, &config.net_list, &config.logger)?;
+    if config.fold_constants {
+        let folded = fold_constants(fabric, &mut net_list)?;
+        config.logger.log(&LogInstance::Text(format!("Folded {folded} constant connections into LUT INITs")))?;
+    }
+    // Route-throughs are only added to LUTs without pins, but before the pathing check relies on them
+    if let Some(cost) = config.lut_route_through_cost {
+        fabric.mark_pins(&net_list);
+        let added = add_route_throughs(fabric, cost);
+        config.logger.log(&LogInstance::Text(format!("Added {added} LUT route-through edges")))?;
+    }
 
-    let router_config = Config::new(config.hist_factor, config.max_iterations);
+    fabric.check_pathing(&mut net_list, config.constant_search_radius, config.skip_unroutable)?;
+    if config.lut_route_through_cost.is_some() {
+        prune_route_throughs(fabric);
+    }
+    Ok((net_list, Config::from(&*config)))
+}
 
-    timing_driven_path_finder(&mut net_list, fabric, &router_config, &config.solver, &config.logger, sta).map(|a| {
-        let new_net_list = net_list.to_external(&fabric.graph);
-        (new_net_list, a)
-    })
+impl<R: RouteNet, L: Logging> From<&RoutingConfig<R, L>> for Config {
+    fn from(config: &RoutingConfig<R, L>) -> Self {
+        Self {
+            bounding_box_margin: config.bounding_box_margin,
+            rip_up: config.rip_up,
+            parallel_nets: config.parallel_nets,
+            net_ordering: config.net_ordering,
+            stop_criteria: config.stop_criteria,
+            stagnation: config.stagnation.clone(),
+            cancellation: config.cancellation.clone(),
+            skip_unroutable: config.skip_unroutable,
+            keep_best_routing: config.keep_best_routing,
+            ..Self::new(config.hist_factor, config.max_iterations)
+        }
+    }
+}
+
+/// Configures the LUTs of the tile manager for the routed `net_list`: borrows the LUTs it routes
+/// through and permutes the inputs of the LUTs whose sinks moved against the `placed` net-list.
+/// Returns the external routed net-list.
+fn finish<L: Logging>(
+    fabric: &mut Fabric,
+    net_list: &NetListInternal,
+    placed: &NetListExternal,
+    route_through: bool,
+    logger: &L,
+) -> FabricResult<NetListExternal> {
+    if route_through {
+        let claimed = claim_route_throughs(fabric, net_list)?;
+        logger.log(&LogInstance::Text(format!("Routed through {claimed} LUTs")))?;
+    }
+    if !net_list.unroutable.is_empty() {
+        let skipped = net_list.unroutable.len();
+        logger.log(&LogInstance::Text(format!("Skipped {skipped} unroutable connections")))?;
+    }
+    let new_net_list = net_list.to_external(&fabric.graph);
+    fabric.tile_manager.set_pin_permutations(&new_net_list.swapped_inputs(placed))?;
+    Ok(new_net_list)
 }
 
-/// Converts Expanded JSON-like structure to a FASM string
+/// Runs [`finish`] for the best routing carried by a failed run, so the tile manager configures the
+/// LUTs the way the FASM of [`crate::PartialRouting::to_fasm`] expects
+fn finish_failed<L: Logging>(
+    fabric: &mut Fabric,
+    placed: &NetListExternal,
+    route_through: bool,
+    logger: &L,
+    mut err: FabricError,
+) -> FabricError {
+    let (FabricError::RoutingMaxIterationsReached { best_routing: Some(best), .. }
+    | FabricError::RoutingStopped { best_routing: Some(best), .. }
+    | FabricError::RoutingInterrupted { best_routing: Some(best), .. }) = &mut err
+    else {
+        return err;
+    };
+    let finished = NetListInternal::from_external(&fabric.graph, &best.net_list)
+        .map_err(FabricError::from)
+        .and_then(|net_list| finish(fabric, &net_list, placed, route_through, logger));
+    match finished {
+        Ok(net_list) => {
+            best.net_list = net_list;
+            err
+        }
+        Err(finish_err) => finish_err,
+    }
+}
+
+/// Logs every warning of the routability pre-analysis so likely unroutable designs are noticed
+/// before the path finder runs into `max_iterations`.
+fn log_routability_warnings<L: Logging>(fabric: &Fabric, net_list: &NetListExternal, logger: &L) -> FabricResult<()> {
+    let report = analyze_routability(fabric, net_list);
+    for warning in &report.warnings {
+        logger.log(&LogInstance::Text(format!("Warning: {warning}")))?;
+    }
+    Ok(())
+}
+
+/// Converts Expanded JSON-like structure to a FASM string.
+/// The truth tables loaded with [`TileManager::load_inits`] are emitted for the routed LUT inputs.
 /// # Errors
 /// This errors when the provided `NetListExternal` is not solved meaning it has a result field
 /// being `None`
 pub fn create_fasm(netlist: &NetListExternal, tile_manager: &TileManager) -> FabricResult<String> {
     let fasm_routing = net_to_fasm(netlist)?;
     let constants = tile_manager.generate_constant_fasm().join("\n");
-    Ok(format!("{fasm_routing}\n{constants}"))
+    let inits = tile_manager.generate_init_fasm();
+    if inits.is_empty() {
+        return Ok(format!("{fasm_routing}\n{constants}"));
+    }
+    let inits = inits.join("\n");
+    Ok(format!("{fasm_routing}\n{constants}\n{inits}"))
 }
 
 /// Creates a Test Netlist by using a `percentage` of all Lut-Outputs and for each `destinations`
 /// using only distinct destinations.
 /// Lut-Inputs
 ///
+/// The net-list is drawn with a random seed, use [`generate_test`] for reproducible net-lists.
+///
 /// # Errors
 /// Can produce File Io erros.
 /// Fails if parameters are bad like trying to use more than 100% of Lut-Outputs
 pub fn create_test(graph: &FabricGraph, percentage: f32, destinations: usize) -> FabricResult<NetListExternal> {
-    let mut rng = rand::rng();
-    let graph_hash = graph.calculate_structure_hash();
-    let (mut inputs, mut outputs) = bucket_luts(graph);
-
-    inputs.shuffle(&mut rng);
-    outputs.shuffle(&mut rng);
-
-    if !(0.0..=1.0).contains(&percentage) {
-        return Err(FabricError::CreatingTestBadParameters(
-            "percentage needs to be between 0-100%".to_string(),
-        ));
-    }
-    if destinations == 0 {
-        return Err(FabricError::CreatingTestBadParameters(
-            "Destinations cannot be 0.".to_string(),
-        ));
-    }
-    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
-    let input_count = (percentage * outputs.len() as f32) as usize;
-    let output_count = input_count * destinations;
-
-
-    if output_count > inputs.len() {
-        return Err(FabricError::CreatingTestBadParameters(
-            "Too many resulting input luts for given parameters."
-                .to_string(),
-        ));
-    }
-
-    let used_outs = inputs.iter().take(output_count).copied().collect::<Vec<NodeId>>();
-
-    let net_list = outputs
-        .iter()
-        .take(input_count)
-        .copied()
-        .zip(used_outs.chunks(destinations))
-        .map(|(signal, sinks)| {
-            NetInternal {
-                sinks: sinks.to_vec(),
-                signal,
-                result: None,
-                intermediate_nodes: None,
-            }
-            .to_external(graph)
-        })
-        .collect::<Vec<NetExternal>>();
-
-    let net_list = NetListExternal {
-        plan: net_list,
-        hash: Some(graph_hash),
+    let config = TestConfig {
+        seed: rand::random(),
+        percentage,
+        fanout: Fanout::Fixed(destinations),
+        ..TestConfig::default()
     };
-
-    Ok(net_list)
+    generate_test(graph, &config)
 }
 
 /// Validates a routing for a given `FabricGraph`
@@ -177,12 +256,27 @@ pub fn validate_routing(graph: &FabricGraph, netlist: &NetListExternal) -> Fabri
     Ok(())
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct RoutingConfigBuilder<R: RouteNet, L: Logging> {
     tile_manager: Option<TileManager>,
     graph: Option<FabricGraph>,
     net_list: Option<NetListExternal>,
     hist_factor: f32,
     max_iterations: usize,
+    heuristic: Heuristic,
+    bounding_box_margin: Option<u8>,
+    rip_up: RipUp,
+    parallel_nets: bool,
+    net_ordering: NetOrdering,
+    stop_criteria: StopCriteria,
+    stagnation: Option<Stagnation>,
+    cancellation: Option<CancellationToken>,
+    fold_constants: bool,
+    constant_search_radius: u8,
+    lut_route_through_cost: Option<f32>,
+    skip_unroutable: bool,
+    keep_best_routing: bool,
+    cost_model: Option<Arc<dyn CostModel>>,
     solver: R,
     logger: L,
 }
@@ -197,6 +291,24 @@ impl RoutingConfigBuilder<SimpleSolver, SimpleLogging> {
             net_list: None,
             hist_factor: 0.1,
             max_iterations: 100,
+            heuristic: Heuristic::None,
+            bounding_box_margin: None,
+            rip_up: RipUp::All,
+            parallel_nets: false,
+            net_ordering: NetOrdering::AsGiven,
+            stop_criteria: StopCriteria {
+                time_limit: None,
+                no_improvement_window: None,
+                predict_unroutable: false,
+            },
+            stagnation: None,
+            cancellation: None,
+            fold_constants: false,
+            constant_search_radius: DEFAULT_CONSTANT_SEARCH_RADIUS,
+            lut_route_through_cost: None,
+            skip_unroutable: false,
+            keep_best_routing: false,
+            cost_model: None,
             solver: SimpleSolver,
             logger: SimpleLogging,
         }
@@ -251,6 +363,120 @@ impl<R: RouteNet, L: Logging> RoutingConfigBuilder<R, L> {
         self
     }
 
+    /// Heuristic used by the path searches of the solvers, see [`Heuristic`]
+    #[must_use]
+    pub const fn heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Restricts the searches of every net to the bounding box of its pins grown by `margin` tiles.
+    /// The box widens while the net fails to route or stays congested.
+    #[must_use]
+    pub const fn bounding_box_margin(mut self, margin: u8) -> Self {
+        self.bounding_box_margin = Some(margin);
+        self
+    }
+
+    /// Selects which nets are re-routed in every iteration, see [`RipUp`]
+    #[must_use]
+    pub const fn rip_up(mut self, rip_up: RipUp) -> Self {
+        self.rip_up = rip_up;
+        self
+    }
+
+    /// Routes batches of nets with disjoint bounding boxes in parallel. The searches of a batch are kept
+    /// inside the boxes, nets without [`Self::bounding_box_margin`] use the box of their pins.
+    #[must_use]
+    pub const fn parallel_nets(mut self, parallel_nets: bool) -> Self {
+        self.parallel_nets = parallel_nets;
+        self
+    }
+
+    /// Order in which the nets are routed in every iteration, see [`NetOrdering`]
+    #[must_use]
+    pub const fn net_ordering(mut self, net_ordering: NetOrdering) -> Self {
+        self.net_ordering = net_ordering;
+        self
+    }
+
+    /// Criteria to give up before `max_iterations`, see [`StopCriteria`]
+    #[must_use]
+    pub const fn stop_criteria(mut self, stop_criteria: StopCriteria) -> Self {
+        self.stop_criteria = stop_criteria;
+        self
+    }
+
+    /// Replaces the default stagnation handling, which re-runs the pre-processing of the
+    /// solver after 200 iterations without improvement
+    #[must_use]
+    pub fn stagnation(mut self, stagnation: Stagnation) -> Self {
+        self.stagnation = Some(stagnation);
+        self
+    }
+
+    /// Lets another thread stop the routing with [`CancellationToken::cancel`]. The routing then
+    /// fails with [`FabricError::RoutingInterrupted`](crate::FabricError::RoutingInterrupted)
+    /// before the next net is routed.
+    #[must_use]
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Folds `VCC0` and `GND0` connections into the truth tables of their LUTs instead of routing
+    /// them. Needs the INITs of the placed design, see [`TileManager::load_inits`].
+    #[must_use]
+    pub const fn fold_constants(mut self, fold_constants: bool) -> Self {
+        self.fold_constants = fold_constants;
+        self
+    }
+
+    /// Tiles around a sink searched for a constant driver when its `VCC0`/`GND0` cannot reach it,
+    /// see [`find_constant_driver`](crate::find_constant_driver)
+    #[must_use]
+    pub const fn constant_search_radius(mut self, radius: u8) -> Self {
+        self.constant_search_radius = radius;
+        self
+    }
+
+    /// Adds edges with the base cost `cost` from the inputs of free LUTs to their outputs, so
+    /// connections can escape congested muxes through a LUT configured as a buffer. `None`, the
+    /// default, disables route-throughs. Like every base cost it is weighted by the criticality of
+    /// the connection, [`DEFAULT_ROUTE_THROUGH_COST`](crate::DEFAULT_ROUTE_THROUGH_COST) keeps
+    /// critical connections away from the LUTs.
+    #[must_use]
+    pub const fn lut_route_through_cost(mut self, cost: Option<f32>) -> Self {
+        self.lut_route_through_cost = cost;
+        self
+    }
+
+    /// Keeps a copy of the routing with the fewest conflicts for the `best_routing` of a failed run.
+    /// Without it the error of a run that reached `max_iterations` or a stop criterion carries the
+    /// routing of the last iteration, which saves a copy of the net-list after every improvement.
+    /// Runs with a [`Self::cancellation`] or a [`StopCriteria::time_limit`] always keep the copy, an
+    /// interrupted iteration leaves no consistent routing behind.
+    #[must_use]
+    pub const fn keep_best_routing(mut self, keep_best_routing: bool) -> Self {
+        self.keep_best_routing = keep_best_routing;
+        self
+    }
+
+    /// Excludes connections without any path to their sink instead of failing the routing. The
+    /// excluded connections are listed with the reason in [`NetListExternal::unroutable`] of the result.
+    #[must_use]
+    pub const fn skip_unroutable(mut self, skip_unroutable: bool) -> Self {
+        self.skip_unroutable = skip_unroutable;
+        self
+    }
+
+    /// Replaces the default [`VprCostModel`](crate::VprCostModel) of the graph
+    #[must_use]
+    pub fn cost_model<C: CostModel + 'static>(mut self, cost_model: C) -> Self {
+        self.cost_model = Some(Arc::new(cost_model));
+        self
+    }
+
     pub fn solver<NewT: RouteNet>(self, solver: NewT) -> RoutingConfigBuilder<NewT, L> {
         RoutingConfigBuilder {
             graph: self.graph,
@@ -258,6 +484,20 @@ impl<R: RouteNet, L: Logging> RoutingConfigBuilder<R, L> {
             net_list: self.net_list,
             hist_factor: self.hist_factor,
             max_iterations: self.max_iterations,
+            heuristic: self.heuristic,
+            bounding_box_margin: self.bounding_box_margin,
+            rip_up: self.rip_up,
+            parallel_nets: self.parallel_nets,
+            net_ordering: self.net_ordering,
+            stop_criteria: self.stop_criteria,
+            stagnation: self.stagnation,
+            cancellation: self.cancellation,
+            fold_constants: self.fold_constants,
+            constant_search_radius: self.constant_search_radius,
+            lut_route_through_cost: self.lut_route_through_cost,
+            skip_unroutable: self.skip_unroutable,
+            keep_best_routing: self.keep_best_routing,
+            cost_model: self.cost_model,
             solver,
             logger: self.logger,
         }
@@ -270,29 +510,78 @@ impl<R: RouteNet, L: Logging> RoutingConfigBuilder<R, L> {
             net_list: self.net_list,
             hist_factor: self.hist_factor,
             max_iterations: self.max_iterations,
+            heuristic: self.heuristic,
+            bounding_box_margin: self.bounding_box_margin,
+            rip_up: self.rip_up,
+            parallel_nets: self.parallel_nets,
+            net_ordering: self.net_ordering,
+            stop_criteria: self.stop_criteria,
+            stagnation: self.stagnation,
+            cancellation: self.cancellation,
+            fold_constants: self.fold_constants,
+            constant_search_radius: self.constant_search_radius,
+            lut_route_through_cost: self.lut_route_through_cost,
+            skip_unroutable: self.skip_unroutable,
+            keep_best_routing: self.keep_best_routing,
+            cost_model: self.cost_model,
             solver: self.solver,
             logger,
         }
     }
     /// Builds the Routing Config
     /// # Errors
-    /// if no graoh or netlist was provided
+    /// if no graoh or netlist was provided, or the stagnation responses widen bounding boxes without
+    /// a bounding box margin
     pub fn build(self) -> FabricResult<RoutingConfig<R, L>> {
-        let graph = self.graph.ok_or("Graph is required to build RoutingConfig")?;
+        let mut graph = self.graph.ok_or("Graph is required to build RoutingConfig")?;
+        if let Some(cost_model) = self.cost_model {
+            graph.set_cost_model(cost_model);
+        }
         let tile_manager = self.tile_manager.ok_or("Graph is required to build RoutingConfig")?;
-        let fabric = Fabric::new(graph, tile_manager);
+        let mut fabric = Fabric::new(graph, tile_manager);
+        fabric.heuristic = self.heuristic;
 
         // If net_list is still None, we could either error or try a default.
         // Given your instructions, we'll error if neither manual nor test netlist was provided.
         let net_list = self
             .net_list
             .ok_or("NetList is required (provide manually or use with_test_netlist)")?;
+        let widens = self.stagnation.as_ref().is_some_and(|stagnation| {
+            stagnation
+                .responses
+                .iter()
+                .any(|response| matches!(response, StagnationResponse::WidenBoundingBoxes(_)))
+        });
+        if widens && self.bounding_box_margin.is_none() {
+            return Err("WidenBoundingBoxes requires a bounding box margin".into());
+        }
+        // A factor below 1 lowers historic costs, the heuristics assume they never shrink
+        let invalid_boost = self.stagnation.as_ref().is_some_and(|stagnation| {
+            stagnation.responses.iter().any(
+                |response| matches!(response, StagnationResponse::HistoricBoost(factor) if !(*factor >= 1.0 && factor.is_finite())),
+            )
+        });
+        if invalid_boost {
+            return Err("HistoricBoost requires a finite factor of at least 1".into());
+        }
 
         Ok(RoutingConfig {
             fabric,
             net_list,
             hist_factor: self.hist_factor,
             max_iterations: self.max_iterations,
+            bounding_box_margin: self.bounding_box_margin,
+            rip_up: self.rip_up,
+            parallel_nets: self.parallel_nets,
+            net_ordering: self.net_ordering,
+            stop_criteria: self.stop_criteria,
+            stagnation: self.stagnation.unwrap_or_default(),
+            cancellation: self.cancellation,
+            fold_constants: self.fold_constants,
+            constant_search_radius: self.constant_search_radius,
+            lut_route_through_cost: self.lut_route_through_cost,
+            skip_unroutable: self.skip_unroutable,
+            keep_best_routing: self.keep_best_routing,
             solver: self.solver,
             logger: self.logger,
         })
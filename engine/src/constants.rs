@@ -0,0 +1,169 @@
+//! Module `constants`
+//!
+//! Handling of nets driven by the constant nodes `VCC0` and `GND0`.
+
+use std::collections::HashSet;
+
+use crate::{
+    Fabric, FabricError, FabricResult,
+    fabric::{
+        node::{NodeId, NodeType},
+        tile_manager::State,
+    },
+    netlist::NetListInternal,
+};
+
+/// Default of [`crate::RoutingConfigBuilder::constant_search_radius`]
+pub const DEFAULT_CONSTANT_SEARCH_RADIUS: u8 = 2;
+
+/// Removes the connections from a constant driver to LUT inputs and folds the constant into the
+/// truth tables of the LUTs instead. Only LUTs with a truth table loaded by
+/// [`crate::TileManager::load_inits`] are folded, nets without any remaining sink are removed.
+///
+/// Returns the number of folded connections.
+///
+/// # Errors
+/// Fails if a sink names a LUT input the `TileManager` does not know
+pub fn fold_constants(fabric: &mut Fabric, net_list: &mut NetListInternal) -> FabricResult<usize> {
+    let mut folded = 0;
+    for net in &mut net_list.plan {
+        let Some(state) = fabric.graph.get_node(net.signal).constant() else {
+            continue;
+        };
+        let mut sinks = Vec::with_capacity(net.sinks.len());
+        for sink in &net.sinks {
+            let node = fabric.graph.get_node(*sink);
+            if let NodeType::LutInput(bel_index) = node.typ
+                && fabric.tile_manager.fold_constant_input(node.tile, bel_index, &node.id, state)?
+            {
+                folded += 1;
+            } else {
+                sinks.push(*sink);
+            }
+        }
+        net.sinks = sinks;
+    }
+    net_list.plan.retain(|net| !net.sinks.is_empty());
+    Ok(folded)
+}
+
+/// Finds a driver of the constant `state` for `sink`.
+///
+/// Used when the constant node of the net cannot reach the sink. All candidates lie within `radius` tiles of the sink.
+/// A single reverse search from the sink over these tiles ranks them by their routing costs to the sink and ends at
+/// the first dedicated wire. The cheapest candidate of the first available kind wins:
+///
+/// 1. dedicated `VCC0`/`GND0` wires of the fabric,
+/// 2. LUTs already borrowed as a `state` tie-off, so one LUT drives many sinks,
+/// 3. free LUTs, the cheapest one is borrowed as a new tie-off.
+///
+/// # Errors
+/// Returns [`FabricError::ConstantDriverNotFound`] if no candidate reaches the sink and
+/// [`FabricError::ConstantDriverNotBorrowable`] if the chosen free LUT cannot be borrowed
+pub fn find_constant_driver(fabric: &mut Fabric, sink: NodeId, state: State, radius: u8) -> FabricResult<NodeId> {
+    let graph = &fabric.graph;
+    let sink_node = graph.get_node(sink);
+    let tile = sink_node.tile;
+    let within_radius = |node: NodeId| {
+        let other = graph.get_node(node).tile;
+        u16::from(other.0.abs_diff(tile.0)) + u16::from(other.1.abs_diff(tile.1)) <= u16::from(radius)
+    };
+    let dedicated = |node: NodeId| graph.get_node(node).constant() == Some(state);
+
+    let (shared, free): (Vec<_>, Vec<_>) = fabric
+        .tile_manager
+        .constant_luts(tile, radius, state)
+        .into_iter()
+        .partition(|(_, _, borrowed)| *borrowed);
+    let output_node = |(lut_tile, output_pin, _): (_, String, bool)| graph.get_node_id(&format!("{lut_tile}.{output_pin}")).copied();
+    let shared = shared.into_iter().filter_map(output_node).collect::<HashSet<NodeId>>();
+    let free = free.into_iter().filter_map(output_node).collect::<HashSet<NodeId>>();
+
+    let reached = graph.reverse_candidates(
+        sink,
+        0.0,
+        within_radius,
+        |node| dedicated(node) || shared.contains(&node) || free.contains(&node),
+        dedicated,
+    );
+    let cheapest = |accept: &dyn Fn(NodeId) -> bool| reached.iter().copied().find(|node| accept(*node));
+    if let Some(driver) = cheapest(&dedicated).or_else(|| cheapest(&|node| shared.contains(&node))) {
+        return Ok(driver);
+    }
+    // Only free LUTs are left
+    let driver = reached.first().copied().ok_or_else(|| FabricError::ConstantDriverNotFound {
+        sink: sink_node.id(),
+        radius,
+    })?;
+    let driver_node = graph.get_node(driver);
+    let (driver_tile, output_pin) = (driver_node.tile, driver_node.id.clone());
+    if !fabric.tile_manager.borrow_constant(driver_tile, &output_pin, state) {
+        return Err(FabricError::ConstantDriverNotBorrowable {
+            tile: driver_tile,
+            output_pin,
+        });
+    }
+    Ok(driver)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FabricGraph, TileManager};
+    use testing_utils::get_test_data_path;
+
+    #[test]
+    fn test_constant_drivers_are_shared() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let mut fabric = Fabric::new(graph, tile_manager);
+        let node = |fabric: &Fabric, id: &str| *fabric.graph.get_node_id(id).unwrap();
+        let (first, second) = (node(&fabric, "X1Y1.LA_I0"), node(&fabric, "X1Y1.LB_I1"));
+
+        let high = find_constant_driver(&mut fabric, first, State::High, 1).unwrap();
+        assert!(matches!(fabric.graph.get_node(high).typ, NodeType::LutOutput(_)));
+        assert_eq!(find_constant_driver(&mut fabric, second, State::High, 1).unwrap(), high);
+        let low = find_constant_driver(&mut fabric, second, State::Low, 1).unwrap();
+        assert_ne!(low, high);
+        assert_eq!(fabric.tile_manager.generate_constant_fasm().len(), 2);
+    }
+
+    #[test]
+    fn test_constant_driver_is_cheapest_candidate() {
+        let radius = 2;
+        for sink in ["X1Y1.LA_EN", "X1Y1.LH_EN", "X2Y2.LC_I2", "X3Y1.LE_I1"] {
+            let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+            let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+            let mut fabric = Fabric::new(graph, tile_manager);
+            let sink = *fabric.graph.get_node_id(sink).unwrap();
+            let tile = fabric.graph.get_node(sink).tile;
+            // Forward searches from every candidate, the dedicated wires come before the LUTs
+            let cheapest = |candidates: Vec<NodeId>| {
+                candidates
+                    .into_iter()
+                    .filter_map(|node| fabric.graph.dijkstra(node, sink, 0.0).map(|(_, cost)| cost))
+                    .min_by(f32::total_cmp)
+            };
+            let dedicated = (0..fabric.graph.nodes.len())
+                .map(NodeId::new)
+                .filter(|node| {
+                    let other = fabric.graph.get_node(*node);
+                    other.constant() == Some(State::High) && other.tile.0.abs_diff(tile.0) + other.tile.1.abs_diff(tile.1) <= radius
+                })
+                .collect();
+            let luts = fabric
+                .tile_manager
+                .constant_luts(tile, radius, State::High)
+                .into_iter()
+                .map(|(lut_tile, output_pin, _)| *fabric.graph.get_node_id(&format!("{lut_tile}.{output_pin}")).unwrap())
+                .collect();
+            let (expected_dedicated, expected) =
+                cheapest(dedicated).map_or_else(|| (false, cheapest(luts).unwrap()), |cost| (true, cost));
+
+            let driver = find_constant_driver(&mut fabric, sink, State::High, radius).unwrap();
+            assert_eq!(fabric.graph.get_node(driver).constant().is_some(), expected_dedicated);
+            let (_, cost) = fabric.graph.dijkstra(driver, sink, 0.0).unwrap();
+            assert!((cost - expected).abs() < 1e-4, "{cost} != {expected}");
+        }
+    }
+}
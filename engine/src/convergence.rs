@@ -0,0 +1,292 @@
+//! Module `convergence`
+//!
+//! Stop criteria and stagnation handling of the path finder. The path finder feeds the conflicts
+//! of every iteration into a [`Convergence`] tracker, which decides when to give up before
+//! `max_iterations` and when the routing got stuck.
+
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Criteria to stop the path finder before `max_iterations`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+pub struct StopCriteria {
+    /// Wall-clock limit of the whole routing run, checked between two nets. An expired limit
+    /// interrupts the routing, see [`Interrupt`].
+    pub time_limit: Option<Duration>,
+    /// Stops when the lowest conflict count did not improve for this many iterations
+    pub no_improvement_window: Option<usize>,
+    /// Stops when the conflict trend does not reach zero conflicts within `max_iterations`, see
+    /// [`Convergence::trend_window`]
+    pub predict_unroutable: bool,
+}
+
+/// Reason the path finder stopped before `max_iterations`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StopReason {
+    /// The time limit of the run was reached, possibly in the middle of an iteration
+    TimeLimit,
+    NoImprovement,
+    PredictedUnroutable,
+    /// The [`CancellationToken`] of the run was cancelled
+    Cancelled,
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TimeLimit => write!(f, "time limit reached"),
+            Self::NoImprovement => write!(f, "no improvement of the conflicts"),
+            Self::PredictedUnroutable => write!(f, "conflict trend predicts an unroutable design"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Handle to stop a routing run from another thread. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests the routing to stop before the next net is routed
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Interrupts a routing run between two nets when it is cancelled or its time limit is reached
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
+}
+
+impl Interrupt {
+    /// Starts the time limit, if any, now
+    pub fn new(cancellation: Option<CancellationToken>, time_limit: Option<Duration>) -> Self {
+        Self {
+            cancellation,
+            deadline: time_limit.and_then(|limit| Instant::now().checked_add(limit)),
+        }
+    }
+
+    /// Returns the reason to interrupt the routing, if any
+    pub fn reason(&self) -> Option<StopReason> {
+        if self.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            return Some(StopReason::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(StopReason::TimeLimit);
+        }
+        None
+    }
+}
+
+/// Action taken when the routing stagnates
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum StagnationResponse {
+    /// Runs the `pre_process` of the solver again
+    PreProcess,
+    /// Multiplies the historic costs of the nodes overused in the last iteration, the factor has to
+    /// be finite and at least 1
+    HistoricBoost(f32),
+    /// Routes the next iteration in a random order, afterwards the net ordering applies again. With
+    /// `NetOrdering::AsGiven` the shuffled order lasts.
+    RandomReorder { seed: u64 },
+    /// Grows the bounding box of every net by this many tiles. Requires a bounding box margin, nets
+    /// without a box already search the whole fabric.
+    WidenBoundingBoxes(u8),
+}
+
+/// When the routing counts as stagnated and how the path finder reacts
+#[derive(Deserialize, Debug, Clone, PartialEq, Serialize)]
+pub struct Stagnation {
+    /// Iterations without a new lowest conflict count after which the routing is stagnated
+    pub window: usize,
+    /// Applied in order whenever the routing stagnates
+    pub responses: Vec<StagnationResponse>,
+}
+
+impl Default for Stagnation {
+    fn default() -> Self {
+        Self {
+            window: 200,
+            responses: vec![StagnationResponse::PreProcess],
+        }
+    }
+}
+
+/// Tracks the conflicts of the iterations of a routing run
+#[derive(Debug)]
+pub struct Convergence {
+    conflicts: Vec<usize>,
+    best: Option<usize>,
+    /// Iterations since the lowest conflict count improved
+    since_best: usize,
+    /// Iterations since the last stagnation response or improvement
+    since_response: usize,
+}
+
+impl Convergence {
+    /// Minimum number of iterations used to fit the conflict trend
+    pub const TREND_WINDOW: usize = 10;
+
+    /// Iterations used to fit the conflict trend, a tenth of `max_iterations` but at least
+    /// [`Self::TREND_WINDOW`]. No prediction is made before that many iterations are recorded.
+    #[must_use]
+    pub fn trend_window(max_iterations: usize) -> usize {
+        (max_iterations / 10).max(Self::TREND_WINDOW)
+    }
+
+    pub const fn new() -> Self {
+        Self {
+            conflicts: Vec::new(),
+            best: None,
+            since_best: 0,
+            since_response: 0,
+        }
+    }
+
+    /// Forgets the recorded iterations, e.g. after new criticalities
+    pub fn restart(&mut self) {
+        self.conflicts.clear();
+        self.best = None;
+        self.since_best = 0;
+        self.since_response = 0;
+    }
+
+    /// Records the conflicts of the finished iteration
+    pub fn record(&mut self, conflicts: usize) {
+        self.conflicts.push(conflicts);
+        if self.best.is_none_or(|best| conflicts < best) {
+            self.best = Some(conflicts);
+            self.since_best = 0;
+            self.since_response = 0;
+        } else {
+            self.since_best += 1;
+            self.since_response += 1;
+        }
+    }
+
+    /// Returns the reason to stop after the recorded iterations, if any
+    pub fn stop_reason(&self, criteria: &StopCriteria, max_iterations: usize) -> Option<StopReason> {
+        if criteria.no_improvement_window.is_some_and(|window| self.since_best >= window) {
+            return Some(StopReason::NoImprovement);
+        }
+        if criteria.predict_unroutable && !self.trend_reaches_zero(max_iterations) {
+            return Some(StopReason::PredictedUnroutable);
+        }
+        None
+    }
+
+    /// Returns `true` once every `window` iterations without improvement
+    pub const fn stagnated(&mut self, stagnation: &Stagnation) -> bool {
+        if stagnation.window > 0 && self.since_response >= stagnation.window {
+            self.since_response = 0;
+            return true;
+        }
+        false
+    }
+
+    /// Fits a line through the conflicts of the last [`Self::trend_window`] iterations and checks if it
+    /// reaches zero before `max_iterations`. Always `true` until enough iterations are recorded.
+    #[allow(clippy::cast_precision_loss)]
+    fn trend_reaches_zero(&self, max_iterations: usize) -> bool {
+        let window = Self::trend_window(max_iterations);
+        if self.conflicts.len() < window {
+            return true;
+        }
+        let window = &self.conflicts[self.conflicts.len() - window..];
+        let n = window.len() as f32;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = window.iter().sum::<usize>() as f32 / n;
+        let (covariance, variance) = window.iter().enumerate().fold((0.0, 0.0), |(cov, var), (x, y)| {
+            let dx = x as f32 - mean_x;
+            (dx.mul_add(*y as f32 - mean_y, cov), dx.mul_add(dx, var))
+        });
+        let slope = covariance / variance;
+        if slope >= 0.0 {
+            return false;
+        }
+        // Conflicts predicted by the line for the last recorded iteration
+        let current = slope.mul_add(n - 1.0 - mean_x, mean_y).max(0.0);
+        let remaining = max_iterations.saturating_sub(self.conflicts.len()) as f32;
+        current / -slope <= remaining
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stagnation_window() {
+        let mut convergence = Convergence::new();
+        let stagnation = Stagnation {
+            window: 3,
+            responses: Vec::new(),
+        };
+        for conflicts in [10, 8, 8, 9] {
+            convergence.record(conflicts);
+            assert!(!convergence.stagnated(&stagnation));
+        }
+        convergence.record(8);
+        assert!(convergence.stagnated(&stagnation));
+        // The window restarts after a response
+        convergence.record(8);
+        assert!(!convergence.stagnated(&stagnation));
+    }
+
+    #[test]
+    fn test_stop_criteria() {
+        let criteria = StopCriteria {
+            no_improvement_window: Some(2),
+            ..StopCriteria::default()
+        };
+        let mut convergence = Convergence::new();
+        convergence.record(5);
+        convergence.record(5);
+        assert_eq!(convergence.stop_reason(&criteria, 100), None);
+        convergence.record(6);
+        assert_eq!(convergence.stop_reason(&criteria, 100), Some(StopReason::NoImprovement));
+
+        let criteria = StopCriteria {
+            predict_unroutable: true,
+            ..StopCriteria::default()
+        };
+        let mut falling = Convergence::new();
+        let mut rising = Convergence::new();
+        for i in 0..Convergence::TREND_WINDOW {
+            falling.record(100 - 5 * i);
+            rising.record(50 + i);
+        }
+        assert_eq!(falling.stop_reason(&criteria, 100), None);
+        // 55 conflicts left at 5 per iteration do not fit into 2 remaining iterations
+        assert_eq!(falling.stop_reason(&criteria, 12), Some(StopReason::PredictedUnroutable));
+        assert_eq!(rising.stop_reason(&criteria, 100), Some(StopReason::PredictedUnroutable));
+
+        // With many iterations left the trend needs a longer history
+        assert_eq!(rising.stop_reason(&criteria, 1000), None);
+        for i in Convergence::TREND_WINDOW..Convergence::trend_window(1000) {
+            rising.record(50 + i);
+        }
+        assert_eq!(rising.stop_reason(&criteria, 1000), Some(StopReason::PredictedUnroutable));
+    }
+}
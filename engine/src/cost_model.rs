@@ -0,0 +1,142 @@
+//! Module `cost_model`
+//!
+//! Congestion costs of the negotiated routing. A [`CostModel`] defines what entering a node
+//! costs, how historic costs accumulate and how the weight of present congestion grows over
+//! the iterations.
+
+use std::fmt::Debug;
+
+use crate::fabric::node::Costs;
+
+/// Costs used by the path searches and the historic update of the path finder
+pub trait CostModel: Debug + Send + Sync {
+    /// Costs of entering a node with the given `costs` over an edge with `base_cost`.
+    /// `present_factor` is the value of [`Self::present_factor`] for the current iteration.
+    fn node_cost(&self, costs: &Costs, base_cost: f32, criticality: f32, present_factor: f32) -> f32;
+
+    /// Accumulates the historic costs of a node after an iteration.
+    /// The usage of the node is reset by the caller afterwards.
+    fn update_historic(&self, costs: &mut Costs, hist_factor: f32);
+
+    /// Weight of the present congestion in `iteration`
+    fn present_factor(&self, iteration: usize) -> f32;
+
+    /// Lower bound of [`Self::node_cost`] for any node entered over an edge with `base_cost`.
+    /// Used by the A* heuristics, so it must never be larger than the real costs.
+    fn lower_bound(&self, base_cost: f32, criticality: f32) -> f32;
+
+    /// Name used for logging
+    fn identifier(&self) -> &'static str;
+}
+
+/// The original cost model: `(1 + historic) * (1 + usage)` blended with the base cost by
+/// criticality. The present congestion weight does not change over the iterations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LegacyCostModel;
+
+impl CostModel for LegacyCostModel {
+    fn node_cost(&self, costs: &Costs, base_cost: f32, criticality: f32, _present_factor: f32) -> f32 {
+        costs.calc_costs(base_cost, criticality)
+    }
+
+    fn update_historic(&self, costs: &mut Costs, hist_factor: f32) {
+        costs.update(hist_factor);
+    }
+
+    fn present_factor(&self, _iteration: usize) -> f32 {
+        1.0
+    }
+
+    fn lower_bound(&self, base_cost: f32, criticality: f32) -> f32 {
+        criticality.mul_add(base_cost, 1.0 - criticality)
+    }
+
+    fn identifier(&self) -> &'static str {
+        "Legacy"
+    }
+}
+
+/// Cost model of VPR's negotiated congestion router.
+///
+/// The congestion costs of a node are `(1 + historic) * (1 + present_factor * overuse)` where
+/// `overuse` is the overuse the node would have if one more net used it. The present factor
+/// starts at `initial_present_factor` and is multiplied by `present_factor_growth` every
+/// iteration up to `max_present_factor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VprCostModel {
+    pub initial_present_factor: f32,
+    pub present_factor_growth: f32,
+    pub max_present_factor: f32,
+}
+
+impl Default for VprCostModel {
+    fn default() -> Self {
+        Self {
+            initial_present_factor: 0.5,
+            present_factor_growth: 1.3,
+            max_present_factor: 1000.0,
+        }
+    }
+}
+
+impl CostModel for VprCostModel {
+    fn node_cost(&self, costs: &Costs, base_cost: f32, criticality: f32, present_factor: f32) -> f32 {
+        let overuse = (f32::from(costs.usage) + 1.0 - costs.capacity).max(0.0);
+        let congestion_cost = (1.0 + costs.historic_cost) * present_factor.mul_add(overuse, 1.0);
+        criticality.mul_add(base_cost, (1.0 - criticality) * congestion_cost)
+    }
+
+    fn update_historic(&self, costs: &mut Costs, hist_factor: f32) {
+        let overuse = f32::from(costs.usage) - costs.capacity;
+        if overuse > 0.0 {
+            costs.historic_cost += hist_factor * overuse;
+        }
+    }
+
+    fn present_factor(&self, iteration: usize) -> f32 {
+        let exponent = i32::try_from(iteration).unwrap_or(i32::MAX);
+        (self.initial_present_factor * self.present_factor_growth.powi(exponent)).min(self.max_present_factor)
+    }
+
+    fn lower_bound(&self, base_cost: f32, criticality: f32) -> f32 {
+        criticality.mul_add(base_cost, 1.0 - criticality)
+    }
+
+    fn identifier(&self) -> &'static str {
+        "VPR"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    const TOLERANCE: f32 = 0.001;
+
+    #[test]
+    fn test_vpr_present_factor_schedule() {
+        let model = VprCostModel::default();
+        assert!((model.present_factor(0) - 0.5).abs() < TOLERANCE);
+        assert!((model.present_factor(1) - 0.65).abs() < TOLERANCE);
+        assert!((model.present_factor(10_000) - 1000.0).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn test_vpr_node_cost() {
+        let model = VprCostModel::default();
+        let mut costs = Costs {
+            historic_cost: 1.0,
+            capacity: 1.0,
+            usage: 0,
+        };
+        // A free node only costs its historic costs
+        assert!((model.node_cost(&costs, 3.0, 0.0, 2.0) - 2.0).abs() < TOLERANCE);
+        costs.usage = 1;
+        assert!((model.node_cost(&costs, 3.0, 0.0, 2.0) - 6.0).abs() < TOLERANCE);
+        assert!((model.node_cost(&costs, 3.0, 1.0, 2.0) - 3.0).abs() < TOLERANCE);
+        assert!(model.node_cost(&costs, 3.0, 0.5, 2.0) >= model.lower_bound(3.0, 0.5));
+
+        costs.usage = 3;
+        model.update_historic(&mut costs, 0.5);
+        assert!((costs.historic_cost - 2.0).abs() < TOLERANCE);
+    }
+}
@@ -1,125 +1,308 @@
-use std::{cmp::Ordering, collections::{BinaryHeap, HashSet}};
+use std::{cell::RefCell, cmp::Ordering, collections::{BinaryHeap, HashSet}};
+
+use serde::Serialize;
+
+use crate::fabric::{
+    bounding_box::BoundingBox,
+    graph::FabricGraph,
+    node::{NodeId, TileId},
+};
+
+/// Estimate of the remaining costs used to guide the path search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum Heuristic {
+    /// Plain Dijkstra, explores the graph in order of the path costs
+    #[default]
+    None,
+    /// A* with a lower bound based on the Manhattan distance to the closest target tile
+    Manhattan,
+    /// A* guided by the `LookaheadTable` of the graph. Falls back to [`Self::Manhattan`]
+    /// for offsets the table does not cover.
+    ///
+    /// Like VPR's lookahead the estimate is not admissible: it is only a lower bound for the
+    /// sampled target pins, and edges added after the table was computed, e.g. LUT route-throughs,
+    /// are not part of it. The searches explore fewer nodes but may miss the cheapest path.
+    Lookahead,
+}
 
-use crate::fabric::{graph::FabricGraph, node::NodeId};
+/// Parameters of a single path search
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SearchOptions {
+    /// Weight of the delay against the congestion costs (0.0 - 1.0)
+    pub criticality: f32,
+    pub heuristic: Heuristic,
+    /// Nodes outside of this box are not expanded, targets are always reachable
+    pub bounding_box: Option<BoundingBox>,
+}
 
-impl FabricGraph {
+impl SearchOptions {
     #[must_use]
-    pub fn dijkstra_find_one(&self, start: NodeId, end: &HashSet<NodeId>, criticallity: f32) -> Option<(NodeId, Vec<NodeId>, f32)> {
-        let mut targets = end.clone();
-        let n = self.nodes.len();
+    pub fn new(criticality: f32) -> Self {
+        Self {
+            criticality,
+            ..Self::default()
+        }
+    }
+}
 
-        let mut dist: Vec<f32> = vec![f32::MAX; n];
-        let mut prev: Vec<Option<NodeId>> = vec![None; n]; // <-- store predecessors
+/// Reusable memory of the path searches.
+///
+/// The distances and predecessors of a search are only valid if their stamp equals the generation
+/// of the search, so starting a new search does not reset all nodes. A workspace adapts to the
+/// number of nodes of the graph it is used with.
+#[derive(Debug, Default)]
+pub struct SearchWorkspace {
+    dist: Vec<f32>,
+    prev: Vec<Option<NodeId>>,
+    stamp: Vec<u32>,
+    generation: u32,
+    heap: BinaryHeap<State>,
+}
 
-        let mut heap = BinaryHeap::new();
+thread_local! {
+    static LOCAL_WORKSPACE: RefCell<SearchWorkspace> = RefCell::new(SearchWorkspace::default());
+}
 
-        let mut max_frontier = 0usize;
+impl SearchWorkspace {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        dist[start] = 0.0;
-        heap.push(State {
-            cost: 0.0,
-            position: start,
-        });
+    /// Runs `f` with the workspace of the current thread. Nested calls get a fresh workspace.
+    pub fn with_local<T>(f: impl FnOnce(&mut Self) -> T) -> T {
+        LOCAL_WORKSPACE.with(|workspace| match workspace.try_borrow_mut() {
+            Ok(mut workspace) => f(&mut workspace),
+            Err(_) => f(&mut Self::default()),
+        })
+    }
 
-        while let Some(State { cost, position }) = heap.pop() {
-            // Track frontier growth
-            if heap.len() > max_frontier {
-                max_frontier = heap.len();
-            }
+    /// Starts a new search over `nodes` nodes
+    fn begin(&mut self, nodes: usize) {
+        self.heap.clear();
+        if self.stamp.len() != nodes {
+            self.dist = vec![f32::MAX; nodes];
+            self.prev = vec![None; nodes];
+            self.stamp = vec![0; nodes];
+            self.generation = 0;
+        }
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.stamp.fill(0);
+            self.generation = 1;
+        }
+    }
 
-            // If popped outdated distance, skip
-            if cost > dist[position] {
-                continue;
-            }
+    fn dist(&self, node: NodeId) -> f32 {
+        if self.stamp[node] == self.generation { self.dist[node] } else { f32::MAX }
+    }
 
-            // Reached destination → reconstruct path
-            if targets.remove(&position) {
-                let mut path_indices = Vec::new();
-                let mut current = Some(position);
+    fn prev(&self, node: NodeId) -> Option<NodeId> {
+        if self.stamp[node] == self.generation { self.prev[node] } else { None }
+    }
 
-                while let Some(idx) = current {
-                    path_indices.push(idx);
-                    current = prev[idx];
-                }
+    fn set(&mut self, node: NodeId, dist: f32, prev: Option<NodeId>) {
+        self.stamp[node] = self.generation;
+        self.dist[node] = dist;
+        self.prev[node] = prev;
+    }
+}
 
-                path_indices.reverse();
+impl FabricGraph {
+    /// Searches the cheapest path from `start` to any node of `end`.
+    /// Returns the reached node, the path and its costs.
+    #[must_use]
+    pub fn dijkstra_find_one(&self, start: NodeId, end: &HashSet<NodeId>, criticallity: f32) -> Option<(NodeId, Vec<NodeId>, f32)> {
+        self.find_one(start, end, &SearchOptions::new(criticallity))
+    }
 
-                return Some((position, path_indices, cost));
-            }
+    /// Searches the cheapest path from `start` to `end`. Returns the path and its costs.
+    #[must_use]
+    pub fn dijkstra(&self, start: NodeId, end: NodeId, criticallity: f32) -> Option<(Vec<NodeId>, f32)> {
+        self.find_path(start, end, &SearchOptions::new(criticallity))
+    }
 
-            // Expand adjacency list
-            for edge in &self.map[position] {
-                let base_cost = edge.cost;
-                let next_cost = cost + self.costs[edge.node_id].calc_costs(base_cost, criticallity);
-                let next_pos = edge.node_id;
+    /// Like [`Self::dijkstra_find_one`] but guided by [`Heuristic::Manhattan`]
+    #[must_use]
+    pub fn astar_find_one(&self, start: NodeId, end: &HashSet<NodeId>, criticallity: f32) -> Option<(NodeId, Vec<NodeId>, f32)> {
+        let options = SearchOptions {
+            heuristic: Heuristic::Manhattan,
+            ..SearchOptions::new(criticallity)
+        };
+        self.find_one(start, end, &options)
+    }
 
-                if next_cost < dist[next_pos] {
-                    dist[next_pos] = next_cost;
-                    prev[next_pos] = Some(position);
-                    heap.push(State {
-                        cost: next_cost,
-                        position: next_pos,
-                    });
-                }
-            }
-        }
+    /// Like [`Self::dijkstra`] but guided by [`Heuristic::Manhattan`]
+    #[must_use]
+    pub fn astar(&self, start: NodeId, end: NodeId, criticallity: f32) -> Option<(Vec<NodeId>, f32)> {
+        let options = SearchOptions {
+            heuristic: Heuristic::Manhattan,
+            ..SearchOptions::new(criticallity)
+        };
+        self.find_path(start, end, &options)
+    }
 
-        None
+    /// Searches the cheapest path from `start` to `end` as configured by `options`.
+    /// Uses the [`SearchWorkspace`] of the current thread.
+    #[must_use]
+    pub fn find_path(&self, start: NodeId, end: NodeId, options: &SearchOptions) -> Option<(Vec<NodeId>, f32)> {
+        SearchWorkspace::with_local(|workspace| self.find_path_in(workspace, start, end, options))
     }
+
+    /// Like [`Self::find_path`] but uses `workspace`
     #[must_use]
-    pub fn dijkstra(&self, start: NodeId, end: NodeId, criticallity: f32) -> Option<(Vec<NodeId>, f32)> {
-        let n = self.nodes.len();
+    pub fn find_path_in(&self, workspace: &mut SearchWorkspace, start: NodeId, end: NodeId, options: &SearchOptions) -> Option<(Vec<NodeId>, f32)> {
+        self.search(workspace, &[(start, 0.0)], &[end], options).map(|(_, path, cost)| (path, cost))
+    }
 
-        let mut dist: Vec<f32> = vec![f32::MAX; n];
-        let mut prev: Vec<Option<NodeId>> = vec![None; n]; 
+    /// Searches the cheapest path from any of `sources` to `end` as configured by `options`.
+    /// Every source starts with its given costs, e.g. to grow a route tree from all of its nodes.
+    /// The returned path begins at the source it was reached from.
+    #[must_use]
+    pub fn find_path_from(&self, sources: &[(NodeId, f32)], end: NodeId, options: &SearchOptions) -> Option<(Vec<NodeId>, f32)> {
+        SearchWorkspace::with_local(|workspace| self.find_path_from_in(workspace, sources, end, options))
+    }
 
-        let mut heap = BinaryHeap::new();
+    /// Like [`Self::find_path_from`] but uses `workspace`
+    #[must_use]
+    pub fn find_path_from_in(
+        &self,
+        workspace: &mut SearchWorkspace,
+        sources: &[(NodeId, f32)],
+        end: NodeId,
+        options: &SearchOptions,
+    ) -> Option<(Vec<NodeId>, f32)> {
+        self.search(workspace, sources, &[end], options).map(|(_, path, cost)| (path, cost))
+    }
 
-        let mut max_frontier = 0usize;
+    /// Searches the cheapest path from `start` to any node of `end` as configured by `options`
+    #[must_use]
+    pub fn find_one(&self, start: NodeId, end: &HashSet<NodeId>, options: &SearchOptions) -> Option<(NodeId, Vec<NodeId>, f32)> {
+        SearchWorkspace::with_local(|workspace| self.find_one_in(workspace, start, end, options))
+    }
 
-        dist[start] = 0.0;
-        heap.push(State {
-            cost: 0.0,
-            position: start,
-        });
+    /// Like [`Self::find_one`] but uses `workspace`
+    #[must_use]
+    pub fn find_one_in(
+        &self,
+        workspace: &mut SearchWorkspace,
+        start: NodeId,
+        end: &HashSet<NodeId>,
+        options: &SearchOptions,
+    ) -> Option<(NodeId, Vec<NodeId>, f32)> {
+        let targets = end.iter().copied().collect::<Vec<NodeId>>();
+        self.search(workspace, &[(start, 0.0)], &targets, options)
+    }
 
-        while let Some(State { cost, position }) = heap.pop() {
-            // Track frontier growth
-            if heap.len() > max_frontier {
-                max_frontier = heap.len();
+    /// Lower bound of the costs of any path from `node` to one of `target_tiles`.
+    ///
+    /// Every hop costs at least `hop_costs`, the lower bound of the cost model for `min_edge_cost`,
+    /// and covers at most `max_edge_span` tiles. The bound is consistent, so A* still finds the
+    /// cheapest path.
+    fn lower_bound(&self, node: NodeId, target_tiles: &[TileId], hop_costs: f32) -> f32 {
+        if self.max_edge_span == 0 {
+            return 0.0;
+        }
+        let tile = self.nodes[node].tile;
+        let distance = target_tiles
+            .iter()
+            .map(|target| u16::from(tile.0.abs_diff(target.0)) + u16::from(tile.1.abs_diff(target.1)))
+            .min()
+            .unwrap_or(0);
+        let hops = distance.div_ceil(u16::from(self.max_edge_span));
+        f32::from(hops) * hop_costs
+    }
+
+    /// Estimate of the costs from `node` to the closest of `targets` taken from the lookahead table.
+    /// Never below [`Self::lower_bound`], but possibly above the real costs, see [`Heuristic::Lookahead`].
+    fn lookahead_estimate(&self, node: NodeId, targets: &[NodeId], criticallity: f32, hop_costs: f32) -> f32 {
+        if targets.contains(&node) {
+            return 0.0;
+        }
+        let tile = self.nodes[node].tile;
+        targets
+            .iter()
+            .map(|target| {
+                let target_node = &self.nodes[*target];
+                let bound = self.lower_bound(node, &[target_node.tile], hop_costs);
+                self.lookahead
+                    .as_ref()
+                    .and_then(|table| table.estimate(node, tile, target_node, criticallity))
+                    .map_or(bound, |estimate| estimate.max(bound))
+            })
+            .fold(f32::MAX, f32::min)
+    }
+
+    fn search(
+        &self,
+        workspace: &mut SearchWorkspace,
+        sources: &[(NodeId, f32)],
+        targets: &[NodeId],
+        options: &SearchOptions,
+    ) -> Option<(NodeId, Vec<NodeId>, f32)> {
+        let criticallity = options.criticality;
+
+        let mut target_tiles = targets.iter().map(|target| self.nodes[*target].tile).collect::<Vec<TileId>>();
+        target_tiles.sort_unstable_by_key(|tile| (tile.0, tile.1));
+        target_tiles.dedup();
+        let hop_costs = self.cost_model.lower_bound(self.min_edge_cost, criticallity);
+        let estimate = |node: NodeId| match options.heuristic {
+            Heuristic::None => 0.0,
+            Heuristic::Manhattan => self.lower_bound(node, &target_tiles, hop_costs),
+            Heuristic::Lookahead => self.lookahead_estimate(node, targets, criticallity, hop_costs),
+        };
+
+        workspace.begin(self.nodes.len());
+
+        for &(source, initial_cost) in sources {
+            if initial_cost < workspace.dist(source) {
+                workspace.set(source, initial_cost, None);
+                workspace.heap.push(State {
+                    cost: initial_cost + estimate(source),
+                    path_cost: initial_cost,
+                    position: source,
+                });
             }
+        }
 
+        while let Some(State { path_cost, position, .. }) = workspace.heap.pop() {
             // If popped outdated distance, skip
-            if cost > dist[position] {
+            if path_cost > workspace.dist(position) {
                 continue;
             }
 
             // Reached destination → reconstruct path
-            if position == end {
+            if targets.contains(&position) {
                 let mut path_indices = Vec::new();
-                let mut current = Some(end);
+                let mut current = Some(position);
 
                 while let Some(idx) = current {
                     path_indices.push(idx);
-                    current = prev[idx];
+                    current = workspace.prev(idx);
                 }
 
                 path_indices.reverse();
 
-                return Some((path_indices, cost));
+                return Some((position, path_indices, path_cost));
             }
 
             // Expand adjacency list
-            for edge in &self.map[position] {
+            for edge in &self.map()[position] {
                 let base_cost = edge.cost;
-                let next_cost = cost + self.costs[edge.node_id].calc_costs(base_cost, criticallity);
+                let next_cost = path_cost + self.node_cost(edge.node_id, base_cost, criticallity);
                 let next_pos = edge.node_id;
+                if let Some(bounding_box) = &options.bounding_box
+                    && !bounding_box.contains(self.nodes[next_pos].tile)
+                    && !targets.contains(&next_pos)
+                {
+                    continue;
+                }
 
-                if next_cost < dist[next_pos] {
-                    dist[next_pos] = next_cost;
-                    prev[next_pos] = Some(position);
-                    heap.push(State {
-                        cost: next_cost,
+                if next_cost < workspace.dist(next_pos) {
+                    workspace.set(next_pos, next_cost, Some(position));
+                    workspace.heap.push(State {
+                        cost: next_cost + estimate(next_pos),
+                        path_cost: next_cost,
                         position: next_pos,
                     });
                 }
@@ -129,47 +312,115 @@ impl FabricGraph {
         None
     }
 
+    /// Reverse search from `end` that only expands the nodes accepted by `within`. Returns the
+    /// nodes accepted by `candidate` in the order of the costs of their cheapest path to `end`.
+    /// The search ends at the first candidate accepted by `stop`.
+    pub(crate) fn reverse_candidates(
+        &self,
+        end: NodeId,
+        criticallity: f32,
+        within: impl Fn(NodeId) -> bool,
+        candidate: impl Fn(NodeId) -> bool,
+        stop: impl Fn(NodeId) -> bool,
+    ) -> Vec<NodeId> {
+        SearchWorkspace::with_local(|workspace| {
+            workspace.begin(self.nodes.len());
+            workspace.set(end, 0.0, None);
+            workspace.heap.push(State {
+                cost: 0.0,
+                path_cost: 0.0,
+                position: end,
+            });
+            let mut found = Vec::new();
+            while let Some(State { path_cost, position, .. }) = workspace.heap.pop() {
+                if path_cost > workspace.dist(position) {
+                    continue;
+                }
+                if position != end && candidate(position) {
+                    found.push(position);
+                    if stop(position) {
+                        break;
+                    }
+                }
+                for edge in &self.map_reversed()[position] {
+                    let next_pos = edge.node_id;
+                    if !within(next_pos) {
+                        continue;
+                    }
+                    // The forward path pays for entering `position` from `next_pos`
+                    let next_cost = path_cost + self.node_cost(position, edge.cost, criticallity);
+                    if next_cost < workspace.dist(next_pos) {
+                        workspace.set(next_pos, next_cost, Some(position));
+                        workspace.heap.push(State {
+                            cost: next_cost,
+                            path_cost: next_cost,
+                            position: next_pos,
+                        });
+                    }
+                }
+            }
+            found
+        })
+    }
+
+    /// Costs of the cheapest path from every node to `start`
     #[must_use]
     pub fn dijkstra_all(&self, start: NodeId) -> Vec<f32> {
-        let n = self.nodes.len();
+        self.reverse_costs(&[start], 0.0)
+    }
 
-        let mut dist: Vec<f32> = vec![f32::MAX; n];
-        let mut heap = BinaryHeap::new();
+    /// Costs of the cheapest path from every node to the closest of `ends` for the given criticallity.
+    /// Like in the forward searches a path pays for every node it enters, so the costs include the
+    /// end but not the node itself. Searches in the workspace of the current thread, only the
+    /// returned costs are allocated.
+    pub(crate) fn reverse_costs(&self, ends: &[NodeId], criticallity: f32) -> Vec<f32> {
+        SearchWorkspace::with_local(|workspace| {
+            self.reverse_search(workspace, ends, criticallity);
+            (0..self.nodes.len()).map(|index| workspace.dist(NodeId::new(index))).collect()
+        })
+    }
 
-        dist[start] = 0.0;
-        heap.push(State {
-            cost: 0.0,
-            position: start,
-        });
+    /// Settles every node that reaches one of `ends`, the costs are left in `workspace`
+    fn reverse_search(&self, workspace: &mut SearchWorkspace, ends: &[NodeId], criticallity: f32) {
+        workspace.begin(self.nodes.len());
+        for &end in ends {
+            workspace.set(end, 0.0, None);
+            workspace.heap.push(State {
+                cost: 0.0,
+                path_cost: 0.0,
+                position: end,
+            });
+        }
 
-        while let Some(State { cost, position }) = heap.pop() {
-            if cost > dist[position] {
+        while let Some(State { cost, position, .. }) = workspace.heap.pop() {
+            if cost > workspace.dist(position) {
                 continue;
             }
 
-            for edge in &self.map_reversed[position] {
-                let base_cost = edge.cost;
-                let next_cost = cost + self.costs[edge.node_id].calc_costs(base_cost, 0.0);
-
+            for edge in &self.map_reversed()[position] {
+                // Like the forward searches, the path pays for entering `position` from `next_pos`
+                let next_cost = cost + self.node_cost(position, edge.cost, criticallity);
                 let next_pos = edge.node_id;
 
-                if next_cost < dist[next_pos] {
-                    dist[next_pos] = next_cost;
-                    heap.push(State {
+                if next_cost < workspace.dist(next_pos) {
+                    workspace.set(next_pos, next_cost, Some(position));
+                    workspace.heap.push(State {
                         cost: next_cost,
+                        path_cost: next_cost,
                         position: next_pos,
                     });
                 }
             }
         }
-
-        dist
     }
 }
 // PriorityQueue state
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 struct State {
+    /// Costs used to order the heap, the path costs plus the heuristic estimate
     cost: f32,
+    /// Costs of the path from the start to `position`
+    path_cost: f32,
     position: NodeId,
 }
 impl PartialEq for State {
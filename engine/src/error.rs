@@ -1,7 +1,7 @@
 use std::{collections::HashSet, io, path::PathBuf};
 use thiserror::Error;
 
-use crate::{IterationResult, fabric::{error::ParseError, node::{Node, TileId}}, netlist::error::MapExternalError, path_finder::CongestionReportExtern};
+use crate::{IterationResult, StopReason, fabric::{error::ParseError, node::{Node, TileId}}, netlist::error::MapExternalError, path_finder::{CongestionReportExtern, PartialRouting}};
 
 // A shorthand for results in your library
 pub type FabricResult<T> = Result<T, FabricError>;
@@ -14,6 +14,9 @@ pub enum FabricError {
     #[error("The Input node: '{input}' does not exist on the Lut: tile: '{tile}' bel_index: '{bel_index}'")]
     LutInputDoesNotExist { tile: TileId, bel_index: char, input: String },
 
+    #[error("The INIT of the Lut: tile: '{tile}' bel_index: '{bel_index}' has {inputs} inputs, the Lut only {pins}.")]
+    LutInitTooWide { tile: TileId, bel_index: char, inputs: u8, pins: usize },
+
     #[error("The STA Tool returned an error.")]
     STAInternalError,
 
@@ -41,7 +44,26 @@ pub enum FabricError {
 
     #[error("Routing has reached its maximum iterations.")]
     RoutingMaxIterationsReached {
-        congestion_report: CongestionReportExtern,
+        congestion_report: Box<CongestionReportExtern>,
+        iteration_report: Vec<IterationResult>,
+        /// Latest routing with the fewest conflicts
+        best_routing: Option<Box<PartialRouting>>,
+    },
+
+    #[error("Routing stopped early: {reason}.")]
+    RoutingStopped {
+        reason: StopReason,
+        congestion_report: Box<CongestionReportExtern>,
+        iteration_report: Vec<IterationResult>,
+        /// Latest routing with the fewest conflicts
+        best_routing: Option<Box<PartialRouting>>,
+    },
+
+    #[error("Routing interrupted: {reason}.")]
+    RoutingInterrupted {
+        reason: StopReason,
+        /// Latest routing with the fewest conflicts, `None` if no iteration was finished
+        best_routing: Option<Box<PartialRouting>>,
         iteration_report: Vec<IterationResult>,
     },
 
@@ -89,6 +111,15 @@ pub enum FabricError {
     #[error("No valid Steiner tree could be constructed for the given sinks.")]
     NoSteinerTreeFound,
 
+    #[error("No constant driver within {radius} tiles reaches the sink {sink}.")]
+    ConstantDriverNotFound { sink: String, radius: u8 },
+
+    #[error("The LUT driving {output_pin} in {tile} cannot be borrowed as a constant driver.")]
+    ConstantDriverNotBorrowable { tile: TileId, output_pin: String },
+
+    #[error("The lookahead table was computed for a different graph.")]
+    LookaheadGraphMismatch,
+
     #[error("Timing could not be met in given maximum sta cycles.")]
     TimingNotMet,
 
@@ -0,0 +1,170 @@
+//! Module `adjacency`
+//!
+//! Compressed sparse row storage of the edges of the fabric graph. The edges of all nodes lie in
+//! one contiguous array, the edges of a node are a slice of it.
+//!
+//! The layout is built for a graph that is read far more often than it changes. Lookups of single
+//! edges go through a second index of one `usize` per edge, which adds about half the memory of the
+//! edges themselves. [`Adjacency::extend`] and [`Adjacency::retain`] repack all edges and rebuild that
+//! index in `O(E log d)`, so collect the changes and apply them in one call.
+
+use std::ops::{Index, Range};
+
+use super::node::{Edge, NodeId};
+
+/// Adjacency lists of a graph in compressed sparse row layout
+#[derive(Debug, Clone)]
+pub struct Adjacency {
+    /// The edges of node `i` are `edges[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<usize>,
+    /// Edges of all nodes in insertion order
+    edges: Vec<Edge>,
+    /// Indices into `edges`, ordered by destination within every node, used by [`Self::find`]
+    by_destination: Vec<usize>,
+}
+
+impl Default for Adjacency {
+    fn default() -> Self {
+        Self::from_lists(Vec::new())
+    }
+}
+
+impl Adjacency {
+    /// Packs the adjacency list of every node, keeping the order of the edges
+    #[must_use]
+    pub fn from_lists(lists: Vec<Vec<Edge>>) -> Self {
+        let mut offsets = Vec::with_capacity(lists.len() + 1);
+        let mut edges = Vec::with_capacity(lists.iter().map(Vec::len).sum());
+        offsets.push(0);
+        for list in lists {
+            edges.extend(list);
+            offsets.push(edges.len());
+        }
+        let mut by_destination = (0..edges.len()).collect::<Vec<usize>>();
+        for range in offsets.windows(2) {
+            // Stable, so the first of several parallel edges is found first
+            by_destination[range[0]..range[1]].sort_by_key(|index| edges[*index].node_id);
+        }
+        Self {
+            offsets,
+            edges,
+            by_destination,
+        }
+    }
+
+    /// Number of nodes
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of edges of all nodes
+    #[must_use]
+    pub const fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn range(&self, node: NodeId) -> Range<usize> {
+        let index = node.index();
+        self.offsets[index]..self.offsets[index + 1]
+    }
+
+    /// Edges leaving `node`
+    #[must_use]
+    pub fn edges(&self, node: NodeId) -> &[Edge] {
+        &self.edges[self.range(node)]
+    }
+
+    /// Edges of every node in order of the node ids
+    #[must_use]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[Edge]> {
+        self.offsets.windows(2).map(|range| &self.edges[range[0]..range[1]])
+    }
+
+    /// Returns the first edge from `start` to `end` in `O(log d)` for `d` edges of `start`
+    #[must_use]
+    pub fn find(&self, start: NodeId, end: NodeId) -> Option<&Edge> {
+        let indices = &self.by_destination[self.range(start)];
+        let first = indices.partition_point(|index| self.edges[*index].node_id < end);
+        indices
+            .get(first)
+            .map(|index| &self.edges[*index])
+            .filter(|edge| edge.node_id == end)
+    }
+
+    /// Unpacks the adjacency list of every node
+    #[must_use]
+    pub fn to_lists(&self) -> Vec<Vec<Edge>> {
+        self.iter().map(<[Edge]>::to_vec).collect()
+    }
+
+    /// Appends `edges` given as start node and edge. Repacks all edges, so add a batch at once.
+    pub fn extend(&mut self, edges: impl IntoIterator<Item = (NodeId, Edge)>) {
+        let mut lists = self.to_lists();
+        for (start, edge) in edges {
+            lists[start].push(edge);
+        }
+        *self = Self::from_lists(lists);
+    }
+
+    /// Keeps only the edges for which `keep` returns `true` for their start node. Repacks all edges,
+    /// so remove a batch at once.
+    pub fn retain(&mut self, mut keep: impl FnMut(NodeId, &Edge) -> bool) {
+        let mut lists = self.to_lists();
+        for (start, list) in lists.iter_mut().enumerate() {
+            list.retain(|edge| keep(NodeId::new(start), edge));
+        }
+        *self = Self::from_lists(lists);
+    }
+}
+
+impl Index<NodeId> for Adjacency {
+    type Output = [Edge];
+    fn index(&self, node: NodeId) -> &Self::Output {
+        self.edges(node)
+    }
+}
+
+impl<'a> IntoIterator for &'a Adjacency {
+    type Item = &'a [Edge];
+    type IntoIter = Box<dyn ExactSizeIterator<Item = &'a [Edge]> + 'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn edge(node_id: usize, cost: f32) -> Edge {
+        Edge {
+            node_id: NodeId::new(node_id),
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_find_and_update() {
+        let mut adjacency = Adjacency::from_lists(vec![vec![edge(2, 1.0), edge(1, 2.0), edge(2, 3.0)], vec![], vec![edge(0, 4.0)]]);
+        assert_eq!(adjacency.len(), 3);
+        assert_eq!(adjacency.edge_count(), 4);
+        let (a, b, c) = (NodeId::new(0), NodeId::new(1), NodeId::new(2));
+        // Parallel edges resolve to the first one
+        assert_eq!(adjacency.find(a, c).map(|edge| edge.cost), Some(1.0));
+        assert_eq!(adjacency.find(a, b).map(|edge| edge.cost), Some(2.0));
+        assert!(adjacency.find(b, a).is_none());
+        assert!(adjacency.find(c, b).is_none());
+
+        adjacency.extend([(b, edge(2, 5.0))]);
+        assert_eq!(adjacency.find(b, c).map(|edge| edge.cost), Some(5.0));
+        adjacency.retain(|start, edge| start != a || edge.node_id != c);
+        assert_eq!(adjacency[a].iter().map(|edge| edge.node_id).collect::<Vec<_>>(), vec![b]);
+        assert_eq!((&adjacency).into_iter().map(<[Edge]>::len).collect::<Vec<_>>(), vec![1, 1, 1]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Module `bounding_box`
+//!
+//! Axis aligned rectangle of tiles used to describe the extent of a net.
+
+use serde::Serialize;
+
+use crate::fabric::node::TileId;
+
+/// Inclusive rectangle of tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct BoundingBox {
+    pub min_x: u8,
+    pub min_y: u8,
+    pub max_x: u8,
+    pub max_y: u8,
+}
+
+impl BoundingBox {
+    /// Creates a `BoundingBox` that only contains `tile`
+    #[must_use]
+    pub const fn new(tile: TileId) -> Self {
+        Self {
+            min_x: tile.0,
+            min_y: tile.1,
+            max_x: tile.0,
+            max_y: tile.1,
+        }
+    }
+
+    /// Smallest `BoundingBox` containing all `tiles`. Returns `None` if `tiles` is empty.
+    pub fn from_tiles<I: IntoIterator<Item = TileId>>(tiles: I) -> Option<Self> {
+        let mut tiles = tiles.into_iter();
+        let first = Self::new(tiles.next()?);
+        Some(tiles.fold(first, |mut bounding_box, tile| {
+            bounding_box.include(tile);
+            bounding_box
+        }))
+    }
+
+    /// Grows the box until it contains `tile`
+    pub fn include(&mut self, tile: TileId) {
+        self.min_x = self.min_x.min(tile.0);
+        self.min_y = self.min_y.min(tile.1);
+        self.max_x = self.max_x.max(tile.0);
+        self.max_y = self.max_y.max(tile.1);
+    }
+
+    #[must_use]
+    pub const fn contains(&self, tile: TileId) -> bool {
+        self.min_x <= tile.0 && tile.0 <= self.max_x && self.min_y <= tile.1 && tile.1 <= self.max_y
+    }
+
+    /// Returns `true` if `other` lies completely inside the box
+    #[must_use]
+    pub const fn covers(&self, other: &Self) -> bool {
+        self.min_x <= other.min_x && other.max_x <= self.max_x && self.min_y <= other.min_y && other.max_y <= self.max_y
+    }
+
+    /// Returns `true` if the boxes share at least one tile
+    #[must_use]
+    pub const fn intersects(&self, other: &Self) -> bool {
+        self.min_x <= other.max_x && other.min_x <= self.max_x && self.min_y <= other.max_y && other.min_y <= self.max_y
+    }
+
+    /// Grows the box by `margin` tiles in every direction without leaving `limit`
+    #[must_use]
+    pub fn expand(&self, margin: u8, limit: &Self) -> Self {
+        Self {
+            min_x: self.min_x.saturating_sub(margin).max(limit.min_x),
+            min_y: self.min_y.saturating_sub(margin).max(limit.min_y),
+            max_x: self.max_x.saturating_add(margin).min(limit.max_x),
+            max_y: self.max_y.saturating_add(margin).min(limit.max_y),
+        }
+    }
+
+    /// Half-perimeter wire length of the box in tiles
+    #[must_use]
+    pub const fn half_perimeter(&self) -> usize {
+        (self.max_x - self.min_x) as usize + (self.max_y - self.min_y) as usize
+    }
+
+    /// Number of tiles covered by the box
+    #[must_use]
+    pub const fn area(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize * (self.max_y - self.min_y + 1) as usize
+    }
+
+    /// Returns `true` if the box has tiles on both sides of the vertical cut between column `x` and `x + 1`
+    #[must_use]
+    pub const fn crosses_vertical_cut(&self, x: u8) -> bool {
+        self.min_x <= x && x < self.max_x
+    }
+
+    /// Returns `true` if the box has tiles on both sides of the horizontal cut between row `y` and `y + 1`
+    #[must_use]
+    pub const fn crosses_horizontal_cut(&self, y: u8) -> bool {
+        self.min_y <= y && y < self.max_y
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_tiles() {
+        let bounding_box = BoundingBox::from_tiles([TileId(2, 3), TileId(1, 5), TileId(4, 4)]).unwrap();
+        assert_eq!(
+            bounding_box,
+            BoundingBox {
+                min_x: 1,
+                min_y: 3,
+                max_x: 4,
+                max_y: 5
+            }
+        );
+        assert_eq!(bounding_box.half_perimeter(), 5);
+        assert_eq!(bounding_box.area(), 12);
+        assert!(bounding_box.contains(TileId(3, 4)));
+        assert!(!bounding_box.contains(TileId(0, 4)));
+        assert!(BoundingBox::from_tiles([]).is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        let limit = BoundingBox::from_tiles([TileId(0, 0), TileId(5, 5)]).unwrap();
+        let bounding_box = BoundingBox::from_tiles([TileId(1, 4), TileId(2, 4)]).unwrap();
+        let expanded = bounding_box.expand(2, &limit);
+        assert_eq!(
+            expanded,
+            BoundingBox {
+                min_x: 0,
+                min_y: 2,
+                max_x: 4,
+                max_y: 5
+            }
+        );
+        assert!(expanded.covers(&bounding_box));
+        assert!(!bounding_box.covers(&expanded));
+        assert!(limit.covers(&expanded.expand(10, &limit)));
+        assert_eq!(expanded.expand(10, &limit), limit);
+    }
+
+    #[test]
+    fn test_intersects() {
+        let bounding_box = BoundingBox::from_tiles([TileId(1, 1), TileId(2, 3)]).unwrap();
+        assert!(bounding_box.intersects(&BoundingBox::new(TileId(2, 3))));
+        assert!(bounding_box.intersects(&BoundingBox::from_tiles([TileId(0, 2), TileId(5, 2)]).unwrap()));
+        assert!(!bounding_box.intersects(&BoundingBox::new(TileId(3, 3))));
+        assert!(!bounding_box.intersects(&BoundingBox::from_tiles([TileId(0, 4), TileId(5, 5)]).unwrap()));
+    }
+
+    #[test]
+    fn test_cut_lines() {
+        let bounding_box = BoundingBox::from_tiles([TileId(1, 1), TileId(2, 1)]).unwrap();
+        assert!(bounding_box.crosses_vertical_cut(1));
+        assert!(!bounding_box.crosses_vertical_cut(2));
+        assert!(!bounding_box.crosses_horizontal_cut(1));
+    }
+}
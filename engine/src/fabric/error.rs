@@ -28,6 +28,9 @@ pub enum ParseError {
     #[error("Missing coordinate prefix '{prefix}' in token: {token}")]
     MissingPrefix { prefix: char, token: String },
 
+    #[error("Invalid LUT INIT: '{content}'")]
+    InvalidInit { content: String },
+
     #[error("Failed to parse '{component}' coordinate: {token}")]
     InvalidCoordinate {
         component: &'static str,
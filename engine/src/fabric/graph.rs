@@ -5,20 +5,27 @@
 //! and computing distances and reversed maps.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs::File,
     io::{BufRead, BufReader},
     path::Path,
+    sync::Arc,
 };
 
 use sha2::{Digest, Sha256};
 
 use crate::{
-    FabricError, FabricResult, NetInternal, NetListInternal, SlackReport,
+    FabricError, FabricResult, NetInternal, NetListInternal, SlackReport, UnroutableSinkInternal,
+    constants::find_constant_driver,
+    cost_model::{CostModel, VprCostModel},
+    dijkstra::{Heuristic, SearchOptions},
+    lookahead::LookaheadTable,
     fabric::{
+        adjacency::Adjacency,
+        bounding_box::BoundingBox,
         node::{Costs, Edge, Node, NodeId},
         parser::{Parser, TimingModel},
-        tile_manager::{State, TileManager},
+        tile_manager::TileManager,
     },
 };
 
@@ -29,60 +36,62 @@ impl Fabric {
             tile_manager,
             graph,
             slack_report: None,
+            heuristic: Heuristic::None,
         }
     }
 
-    pub(crate) fn check_pathing(&mut self, net_list: &mut NetListInternal) -> FabricResult<()> {
+    /// Marks the pins of `net_list` as used and replaces the drivers of constant connections the
+    /// `VCC0`/`GND0` node cannot reach, see [`find_constant_driver`].
+    ///
+    /// With `skip_unroutable` connections without any path are moved to [`NetListInternal::unroutable`]
+    /// instead of failing.
+    pub(crate) fn check_pathing(
+        &mut self,
+        net_list: &mut NetListInternal,
+        constant_search_radius: u8,
+        skip_unroutable: bool,
+    ) -> FabricResult<()> {
         let net_list_flatten = net_list
             .plan
             .iter()
             .flat_map(|a| a.sinks.iter().map(|v| (a.signal, *v)))
-            .collect::<HashSet<(NodeId, NodeId)>>();
+            .collect::<BTreeSet<(NodeId, NodeId)>>();
 
-        for (signal, sink) in &net_list_flatten {
-            // Check the Source
-            self.check_and_mark_node(*signal);
-            self.check_and_mark_node(*sink);
-        }
+        self.mark_pins(net_list);
 
-        let mut optimized_net = HashSet::new();
+        let mut optimized_net = BTreeSet::new();
+        let mut unroutable = std::mem::take(&mut net_list.unroutable);
         for (signal, sink) in &net_list_flatten {
             let signal_node = self.graph.get_node(*signal);
-            if self.graph.dijkstra(*signal, *sink, 0.0).is_some() {
+            if self.graph.find_path(*signal, *sink, &self.search_options(None, 0.0)).is_some() {
                 optimized_net.insert((*signal, *sink));
                 continue;
             }
-            let state = match signal_node.id.as_str() {
-                "VCC0" => Some(State::High),
-                "GND0" => Some(State::Low),
-                _ => None,
-            };
-            let sink_node = self.graph.get_node(*sink);
-            let state = state.ok_or_else(|| {
-                FabricError::Other(format!(
-                    "Cannot find a routing for net {} -> {}",
-                    sink_node.id(),
-                    signal_node.id()
-                ))
-            })?;
-            let tile = sink_node.tile;
-            let new_source_name = self
-                .tile_manager
-                .request_constant(tile, state)
-                .ok_or_else(|| FabricError::Other("Fabric exhausted: No free LUTs for constants".into()))?;
-
-            let node_id_str = format!("X{}Y{}.{}", new_source_name.0.0, new_source_name.0.1, new_source_name.1);
-            let node = self.graph.get_node_id(&node_id_str).unwrap();
-            // 4. Re-run Dijkstra with the new local source
-            let _ = self
-                .graph
-                .dijkstra(*node, *sink, 0.0)
-                .ok_or_else(|| FabricError::Other(format!("Even local constant {new_source_name:?} couldn't reach sink")))?;
-            optimized_net.insert((*node, *sink));
+            let driver = signal_node
+                .constant()
+                .ok_or_else(|| {
+                    FabricError::Other(format!(
+                        "Cannot find a routing for net {} -> {}",
+                        self.graph.get_node(*sink).id(),
+                        signal_node.id()
+                    ))
+                })
+                .and_then(|state| find_constant_driver(self, *sink, state, constant_search_radius));
+            match driver {
+                Ok(driver) => {
+                    optimized_net.insert((driver, *sink));
+                }
+                Err(err) if skip_unroutable => unroutable.push(UnroutableSinkInternal {
+                    signal: *signal,
+                    sink: *sink,
+                    reason: err.to_string(),
+                }),
+                Err(err) => return Err(err),
+            }
         }
 
-        // 1. Group sinks by their signal (source)
-        let mut grouped_nets: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        // 1. Group sinks by their signal (source), ordered by node id so the plan does not change between runs
+        let mut grouped_nets: BTreeMap<NodeId, Vec<NodeId>> = BTreeMap::new();
 
         for (signal, sink) in optimized_net {
             grouped_nets.entry(signal).or_default().push(sink);
@@ -96,11 +105,36 @@ impl Fabric {
                 sinks,
                 result: None,
                 intermediate_nodes: None,
+                bounding_box: None,
             })
             .collect();
-        *net_list = NetListInternal { plan: new_plan };
+        *net_list = NetListInternal {
+            plan: new_plan,
+            unroutable,
+        };
         Ok(())
     }
+    /// Marks the LUTs and LUT inputs of the signals and sinks of `net_list` as used
+    pub(crate) fn mark_pins(&mut self, net_list: &NetListInternal) {
+        for net in &net_list.plan {
+            // Check the Source
+            self.check_and_mark_node(net.signal);
+            for sink in &net.sinks {
+                self.check_and_mark_node(*sink);
+            }
+        }
+    }
+
+    /// Options for a search of a net restricted to `bounding_box` using the heuristic of the fabric
+    #[must_use]
+    pub const fn search_options(&self, bounding_box: Option<BoundingBox>, criticality: f32) -> SearchOptions {
+        SearchOptions {
+            criticality,
+            heuristic: self.heuristic,
+            bounding_box,
+        }
+    }
+
     #[allow(clippy::missing_panics_doc)]
     pub fn check_and_mark_node(&mut self, node_id: NodeId) {
         let node = self.graph.get_node(node_id);
@@ -123,21 +157,51 @@ pub struct Fabric {
     pub tile_manager: TileManager,
     pub graph: FabricGraph,
     pub slack_report: Option<SlackReport>,
+    /// Heuristic the solvers use for their path searches
+    pub heuristic: Heuristic,
 }
 
 
 /// Representation of the FPGA fabric graph
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct FabricGraph {
     pub nodes: Vec<Node>,
     /// Costs associated with each node
     pub costs: Vec<Costs>,
-    /// Forward adjacency list
-    pub map: Vec<Vec<Edge>>,
-    /// Reversed adjacency list
-    pub map_reversed: Vec<Vec<Edge>>,
+    /// Forward adjacency list, see [`Self::map`]
+    map: Adjacency,
+    /// Reversed adjacency list, see [`Self::map_reversed`]
+    map_reversed: Adjacency,
     /// Index of String ids from PIPS file to internal `NodeId`
     pub index: HashMap<String, NodeId>,
+    /// Smallest base cost of all edges, used as lower bound by A*
+    pub min_edge_cost: f32,
+    /// Largest Manhattan distance in tiles covered by a single edge, used as lower bound by A*
+    pub max_edge_span: u8,
+    /// Precomputed lookahead used by `Heuristic::Lookahead`
+    pub lookahead: Option<LookaheadTable>,
+    /// Congestion costs of the nodes
+    pub cost_model: Arc<dyn CostModel>,
+    /// Weight of the present congestion in the current iteration, see [`CostModel::present_factor`]
+    pub present_factor: f32,
+}
+
+impl Default for FabricGraph {
+    fn default() -> Self {
+        let cost_model = Arc::new(VprCostModel::default());
+        Self {
+            nodes: Vec::default(),
+            costs: Vec::default(),
+            map: Adjacency::default(),
+            map_reversed: Adjacency::default(),
+            index: HashMap::default(),
+            min_edge_cost: 0.0,
+            max_edge_span: 0,
+            lookahead: None,
+            present_factor: cost_model.present_factor(0),
+            cost_model,
+        }
+    }
 }
 
 impl FabricGraph {
@@ -145,6 +209,61 @@ impl FabricGraph {
     pub fn get_node(&self, node_id: NodeId) -> &Node {
         &self.nodes[node_id]
     }
+    /// Costs of entering `node_id` over an edge with `base_cost` as defined by the cost model
+    #[must_use]
+    pub fn node_cost(&self, node_id: NodeId, base_cost: f32, criticality: f32) -> f32 {
+        self.cost_model
+            .node_cost(&self.costs[node_id], base_cost, criticality, self.present_factor)
+    }
+
+    /// Replaces the cost model and resets the present factor to its first iteration
+    pub fn set_cost_model(&mut self, cost_model: Arc<dyn CostModel>) {
+        self.present_factor = cost_model.present_factor(0);
+        self.cost_model = cost_model;
+    }
+
+    /// Forward adjacency list. Indexing it with a node gives the edges leaving the node, iterating
+    /// it gives the edges of every node in order of the node ids.
+    #[must_use]
+    pub const fn map(&self) -> &Adjacency {
+        &self.map
+    }
+
+    /// Reversed adjacency list, the edges entering every node
+    #[must_use]
+    pub const fn map_reversed(&self) -> &Adjacency {
+        &self.map_reversed
+    }
+
+    /// Replaces all edges by the forward and reversed adjacency lists of a parsed graph
+    pub(crate) fn set_edges(&mut self, map: Vec<Vec<Edge>>, map_reversed: Vec<Vec<Edge>>) {
+        self.map = Adjacency::from_lists(map);
+        self.map_reversed = Adjacency::from_lists(map_reversed);
+        self.update_search_bounds();
+    }
+
+    /// Adds the edges given as start, end and cost in both directions. Every call repacks all edges,
+    /// see [`Adjacency::extend`], so add them in one batch.
+    pub(crate) fn add_edges(&mut self, edges: &[(NodeId, NodeId, f32)]) {
+        self.map
+            .extend(edges.iter().map(|(start, end, cost)| (*start, Edge { node_id: *end, cost: *cost })));
+        self.map_reversed
+            .extend(edges.iter().map(|(start, end, cost)| (*end, Edge { node_id: *start, cost: *cost })));
+        self.update_search_bounds();
+    }
+
+    /// Keeps only the edges for which `keep` returns `true` for their start and end node in both
+    /// directions. Every call repacks all edges, see [`Adjacency::retain`].
+    ///
+    /// Returns the number of removed edges.
+    pub(crate) fn retain_edges(&mut self, mut keep: impl FnMut(NodeId, NodeId) -> bool) -> usize {
+        let before = self.map.edge_count();
+        self.map.retain(|start, edge| keep(start, edge.node_id));
+        self.map_reversed.retain(|end, edge| keep(edge.node_id, end));
+        self.update_search_bounds();
+        before - self.map.edge_count()
+    }
+
     #[must_use]
     pub fn get_costs(&self, node_id: NodeId) -> &Costs {
         &self.costs[node_id]
@@ -158,7 +277,7 @@ impl FabricGraph {
     /// # Panics
     /// This panics when the graph does not contain that edge
     pub fn get_edge_panic(&self, start: NodeId, end: NodeId) -> &Edge {
-        self.map[start].iter().find(|a| a.node_id == end).unwrap_or_else(|| {
+        self.map.find(start, end).unwrap_or_else(|| {
             panic!(
                 "Graph did not contain the edge: a: {}, b: {}",
                 start.name(self),
@@ -172,9 +291,8 @@ impl FabricGraph {
     /// # Errors
     /// This fails when the graph does not contain that edge
     pub fn get_edge(&self, start: NodeId, end: NodeId) -> FabricResult<&Edge> {
-        self.map[start]
-            .iter()
-            .find(|a| a.node_id == end)
+        self.map
+            .find(start, end)
             .ok_or_else(|| FabricError::EdgeDoesNotExist {
                 start: start.name(self),
                 end: end.name(self),
@@ -219,6 +337,29 @@ impl FabricGraph {
         Ok(pips_parser.build())
     }
 
+    /// Recalculates `min_edge_cost` and `max_edge_span` from the edges of the graph
+    pub fn update_search_bounds(&mut self) {
+        let edges = self
+            .nodes
+            .iter()
+            .zip(&self.map)
+            .flat_map(|(start, edges)| edges.iter().map(move |edge| (start, edge)));
+        let (mut min_edge_cost, mut max_edge_span) = (None::<f32>, 0);
+        for (start, edge) in edges {
+            let end = &self.nodes[edge.node_id];
+            min_edge_cost = Some(min_edge_cost.map_or(edge.cost, |cost| cost.min(edge.cost)));
+            max_edge_span = max_edge_span.max(start.tile.0.abs_diff(end.tile.0) + start.tile.1.abs_diff(end.tile.1));
+        }
+        self.min_edge_cost = min_edge_cost.unwrap_or(0.0).max(0.0);
+        self.max_edge_span = max_edge_span;
+    }
+
+    /// Smallest box containing every tile of the fabric
+    #[must_use]
+    pub fn extent(&self) -> Option<BoundingBox> {
+        BoundingBox::from_tiles(self.nodes.iter().map(|node| node.tile))
+    }
+
     pub fn reset_usage(&mut self) {
         self.costs.iter_mut().for_each(|a| a.usage = 0);
     }
@@ -275,7 +416,7 @@ pub fn bucket_luts(graph: &FabricGraph) -> (Vec<NodeId>, Vec<NodeId>) {
 
 #[cfg(test)]
 mod test {
-    use crate::fabric::node::TileId;
+    use crate::fabric::{node::TileId, tile_manager::State};
 
     use super::*;
     use testing_utils::get_test_data_path;
@@ -308,6 +449,12 @@ mod test {
     fn test_mark_borrowed() {
         let test_file = get_test_data_path("bel_8x8.txt");
         let mut tile_manager = TileManager::from_file(&test_file).unwrap();
-        let _ = tile_manager.request_constant(TileId(1, 1), State::High).unwrap();
+        assert!(tile_manager.borrow_constant(TileId(1, 1), "LA_O", State::High));
+        // A tie-off is shared by sinks of the same constant only
+        assert!(tile_manager.borrow_constant(TileId(1, 1), "LA_O", State::High));
+        assert!(!tile_manager.borrow_constant(TileId(1, 1), "LA_O", State::Low));
+        // A LUT with a truth table belongs to the design even without a net
+        tile_manager.load_inits("X1Y1.B.INIT[15:0] = 16'h8000").unwrap();
+        assert!(!tile_manager.borrow_constant(TileId(1, 1), "LB_O", State::High));
     }
 }
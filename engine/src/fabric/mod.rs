@@ -1,3 +1,5 @@
+pub mod adjacency;
+pub mod bounding_box;
 pub mod graph;
 pub mod tile_manager;
 pub mod node;
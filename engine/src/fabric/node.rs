@@ -9,17 +9,24 @@ use std::cmp::Ordering;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 
 use super::error::ParseError;
-use crate::FabricGraph;
+use crate::{FabricGraph, fabric::tile_manager::State};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NodeId(pub(super) NodeIdType);
 
 impl NodeId {
-    pub(super) fn new(id: usize) -> Self {
+    pub(crate) fn new(id: usize) -> Self {
         let x = NodeIdType::try_from(id)
             .expect("The id space is too small to create this NodeId. Try building the engine with a internal NodeId");
         Self(x)
     }
+    /// Position of the node in the node arrays of the graph
+    pub(crate) const fn index(self) -> usize {
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::unnecessary_cast)]
+        let index = self.0 as usize;
+        index
+    }
     pub(crate) fn as_node(self, graph: &FabricGraph) -> Node {
         graph.get_node(self).clone()
     }
@@ -47,8 +54,17 @@ impl<T> std::ops::IndexMut<NodeId> for Vec<T> {
     }
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct TileId(pub u8, pub u8);
+
+impl Serialize for TileId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
 type NodeIdType = u32;
 
 /// Programmable Connectio between nodes
@@ -181,6 +197,15 @@ impl Node {
     pub fn id(&self) -> String {
         format!("{}.{}", self.tile, self.id)
     }
+    /// Returns the driven state if the node is a constant driver (`VCC0` or `GND0`)
+    #[must_use]
+    pub fn constant(&self) -> Option<State> {
+        match self.id.as_str() {
+            "VCC0" => Some(State::High),
+            "GND0" => Some(State::Low),
+            _ => None,
+        }
+    }
 }
 
 impl Display for TileId {
@@ -244,6 +269,7 @@ impl Costs {
     }
 
     /// Calculate total cost for this node
+    #[must_use]
     pub fn calc_costs(&self, base_cost: f32, criticallity: f32) -> f32 {
         #[allow(clippy::cast_precision_loss)]
         let casted_usage = f32::from(self.usage);
@@ -15,6 +15,9 @@ struct PipsLine {
 
 pub struct Parser {
     graph: FabricGraph,
+    /// Edges of every node, packed into the graph by [`Self::build`]
+    map: Vec<Vec<Edge>>,
+    map_reversed: Vec<Vec<Edge>>,
     timing_model: Option<TimingModel>,
 }
 
@@ -31,6 +34,8 @@ impl Parser {
     pub fn new() -> Self {
         Self {
             graph: FabricGraph::default(),
+            map: Vec::new(),
+            map_reversed: Vec::new(),
             timing_model: None,
         }
     }
@@ -60,8 +65,8 @@ impl Parser {
         let sid = self.get_or_create_node(&start_node);
         let eid = self.get_or_create_node(&end_node);
 
-        self.graph.map[sid].push(Edge { node_id: eid, cost });
-        self.graph.map_reversed[eid].push(Edge { node_id: sid, cost });
+        self.map[sid].push(Edge { node_id: eid, cost });
+        self.map_reversed[eid].push(Edge { node_id: sid, cost });
 
         Ok(())
     }
@@ -75,12 +80,13 @@ impl Parser {
         self.graph.nodes.push(node.clone());
         self.graph.costs.push(Costs::new());
 
-        self.graph.map.push(Vec::new());
-        self.graph.map_reversed.push(Vec::new());
+        self.map.push(Vec::new());
+        self.map_reversed.push(Vec::new());
 
         id
     }
-    pub fn build(self) -> FabricGraph {
+    pub fn build(mut self) -> FabricGraph {
+        self.graph.set_edges(self.map, self.map_reversed);
         self.graph
     }
 }
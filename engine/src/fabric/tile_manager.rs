@@ -1,13 +1,13 @@
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     fs::File,
     io::{BufRead, BufReader},
     path::Path,
 };
 
-use crate::{FabricError, FabricResult, fabric::node::TileId};
+use crate::{FabricError, FabricResult, LutInit, Swap, fabric::node::TileId};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum State {
     High,
     Low,
@@ -18,6 +18,8 @@ pub enum LutState {
     Free,
     Used,
     Borrowed(State),
+    /// Borrowed as a buffer that forwards one input to the output, see [`TileManager::borrow_route_through`]
+    RouteThrough,
 }
 #[derive(Debug, Eq, PartialEq)]
 pub enum LutInputState {
@@ -31,6 +33,10 @@ pub struct Lut {
     state: LutState,
     output_pin: String,
     input_pin: [(String, LutInputState); 4],
+    /// Truth table of the placed design, see [`TileManager::load_inits`]
+    init: Option<LutInit>,
+    /// Input `k` of the placed design is routed to input `permutation[k]`
+    permutation: [u8; 4],
 }
 
 #[derive(Debug)]
@@ -40,7 +46,7 @@ pub struct Tile {
 }
 
 #[derive(Debug)]
-pub struct TileManager(pub HashMap<TileId, Tile>);
+pub struct TileManager(pub BTreeMap<TileId, Tile>);
 
 impl TileManager {
     /// Reads from the bel.txt file and creates a `TileManager`
@@ -52,7 +58,7 @@ impl TileManager {
             source,
         })?;
         let reader = BufReader::new(file);
-        let mut tiles: HashMap<TileId, Tile> = HashMap::new();
+        let mut tiles: BTreeMap<TileId, Tile> = BTreeMap::new();
 
         for (line_number, line) in reader.lines().enumerate() {
             let line = line.map_err(|source| FabricError::Io {
@@ -90,6 +96,8 @@ impl TileManager {
                     (parts[7].to_string(), LutInputState::Free),
                     (parts[8].to_string(), LutInputState::Free),
                 ],
+                init: None,
+                permutation: [0, 1, 2, 3],
             };
 
             // Insert into the tile manager
@@ -105,6 +113,12 @@ impl TileManager {
 
         Ok(Self(tiles))
     }
+    /// Returns the number of LUT input pins of every tile
+    pub fn lut_input_supply(&self) -> impl Iterator<Item = (TileId, usize)> {
+        self.0
+            .iter()
+            .map(|(tile_id, tile)| (*tile_id, tile.luts.iter().map(|lut| lut.input_pin.len()).sum()))
+    }
     /// Internal helper to find a LUT by index within a specific tile
     fn find_lut_mut(&mut self, tile_id: TileId, bel_index: char) -> Option<&mut Lut> {
         self.0
@@ -179,37 +193,95 @@ impl TileManager {
         }
     }
 
-    /// Searched the tile for a lut that can be set to either all 0's or 1's to produce a *VCC* or
-    /// *GND* signal
-    pub fn request_constant(&mut self, start_tile: TileId, state: State) -> Option<(TileId, String)> {
-        let search_order = [
-            start_tile,
-            TileId(start_tile.0 + 1, start_tile.1), // East
-            TileId(start_tile.0, start_tile.1 + 1), // North
-        ];
-
-        for &tid in &search_order {
-            if let Some(tile) = self.0.get_mut(&tid) {
-                let existing = tile
-                    .luts
-                    .iter()
-                    .find(|l| matches!(&l.state, LutState::Borrowed(s) if s == &state));
+    /// LUTs within `radius` tiles (Manhattan distance) of `tile` that can drive `state`.
+    /// Returns the tile, the output pin and `true` for LUTs already borrowed as a `state` driver,
+    /// which can be shared, or `false` for free LUTs. Free LUTs with a loaded truth table belong to
+    /// the design, e.g. a LUT that only feeds its FF, and are never offered.
+    #[must_use]
+    pub fn constant_luts(&self, tile: TileId, radius: u8, state: State) -> Vec<(TileId, String, bool)> {
+        self.0
+            .values()
+            .filter(|other| u16::from(other.id.0.abs_diff(tile.0)) + u16::from(other.id.1.abs_diff(tile.1)) <= u16::from(radius))
+            .flat_map(|other| {
+                other.luts.iter().filter_map(move |lut| match &lut.state {
+                    LutState::Borrowed(borrowed) if borrowed == &state => Some((other.id, lut.output_pin.clone(), true)),
+                    LutState::Free if lut.init.is_none() => Some((other.id, lut.output_pin.clone(), false)),
+                    _ => None,
+                })
+            })
+            .collect()
+    }
 
-                if let Some(lut) = existing {
-                    return Some((tile.id, lut.output_pin.clone()));
-                }
+    /// Borrows the LUT driving `output_pin` in `tile` as a constant `state` driver.
+    /// Returns `false` if the LUT is neither free nor already driving `state`, or has a loaded truth table.
+    pub fn borrow_constant(&mut self, tile: TileId, output_pin: &str, state: State) -> bool {
+        let Some(lut) = self
+            .0
+            .get_mut(&tile)
+            .and_then(|tile| tile.luts.iter_mut().find(|lut| lut.output_pin == output_pin))
+        else {
+            return false;
+        };
+        match lut.state {
+            LutState::Free if lut.init.is_none() => {
+                lut.state = LutState::Borrowed(state);
+                true
+            }
+            LutState::Borrowed(borrowed) => borrowed == state,
+            LutState::Free | LutState::Used | LutState::RouteThrough => false,
+        }
+    }
 
-                let free_lut_index = tile.luts.iter().position(|l| matches!(l.state, LutState::Free));
+    /// Free LUTs without a loaded truth table that can forward an input to their output.
+    /// Returns the tile, the input pins and the output pin of every LUT.
+    #[must_use]
+    pub fn route_through_luts(&self) -> Vec<(TileId, Vec<String>, String)> {
+        self.0
+            .values()
+            .flat_map(|tile| {
+                tile.luts
+                    .iter()
+                    .filter(|lut| matches!(lut.state, LutState::Free) && lut.init.is_none())
+                    .map(|lut| {
+                        let inputs = lut.input_pin.iter().map(|(pin, _)| pin.clone()).collect();
+                        (tile.id, inputs, lut.output_pin.clone())
+                    })
+            })
+            .collect()
+    }
 
-                if let Some(idx) = free_lut_index {
-                    let lut = &mut tile.luts[idx];
-                    lut.state = LutState::Borrowed(state);
-                    return Some((tile.id, lut.output_pin.clone()));
-                }
+    /// Borrows the free LUT `bel_index` as a buffer of `input`, its truth table becomes the
+    /// identity of that input. A LUT already routed through keeps its first input.
+    /// Returns `false` if the LUT is neither free nor routed through.
+    ///
+    /// # Errors
+    /// `LutDoesNotExist`, `LutInputDoesNotExist`: if there is no such LUT input
+    pub fn borrow_route_through(&mut self, tile: TileId, bel_index: char, input: &str) -> FabricResult<bool> {
+        let lut = self
+            .find_lut_mut(tile, bel_index)
+            .ok_or(FabricError::LutDoesNotExist { tile, bel_index })?;
+        let pin = lut
+            .input_pin
+            .iter()
+            .position(|(pin, _)| pin == input)
+            .ok_or_else(|| FabricError::LutInputDoesNotExist {
+                tile,
+                bel_index,
+                input: input.to_string(),
+            })?;
+        match lut.state {
+            LutState::Free => {
+                #[allow(clippy::cast_possible_truncation)]
+                let (inputs, pin) = (lut.input_pin.len() as u8, pin as u8);
+                lut.state = LutState::RouteThrough;
+                lut.init = Some(LutInit::identity(inputs, pin));
+                Ok(true)
             }
+            LutState::RouteThrough => Ok(true),
+            LutState::Used | LutState::Borrowed(_) => Ok(false),
         }
-        None
     }
+
     /// Iterates through all tiles and generates FASM configuration strings
     /// for LUTs that were borrowed as constant drivers.
     #[must_use]
@@ -219,38 +291,181 @@ impl TileManager {
         for (tile_id, tile) in &self.0 {
             for lut in &tile.luts {
                 if let LutState::Borrowed(state) = &lut.state {
-                    // Example FASM Format: Tile_X1Y1.LC_A.INIT[15:0] = 16'h0000
-                    let init_val = match state {
-                        State::Low => "16'b0000000000000000",
-                        State::High => "16'h1111111111111111",
+                    // A loaded truth table sets the width of the LUT, otherwise every pin is an input
+                    #[allow(clippy::cast_possible_truncation)]
+                    let inputs = lut.init.map_or(lut.input_pin.len() as u8, |init| init.inputs());
+                    let bits = match state {
+                        State::Low => 0,
+                        State::High => u64::MAX,
                     };
-
-                    // We use the bel_index (e.g., 'A', 'B') to specify which LUT in the tile
-                    let line = format!("X{}Y{}.{}.INIT[15:0] = {}", tile_id.0, tile_id.1, lut.bel_index, init_val);
-
-                    fasm_lines.push(line);
+                    fasm_lines.push(LutInit::new(bits, inputs).to_fasm(*tile_id, lut.bel_index));
                 }
             }
         }
         fasm_lines
     }
 
-    /// Returns the free lut inputs of a specified lut and sets them as used.
-    pub(crate) fn get_free_lut_inputs(&mut self, tile: TileId, bel_index: char) -> FabricResult<Vec<String>> {
+    /// Reads the LUT truth tables of a placed design from the `INIT` lines of `fasm`, e.g. the
+    /// `ffs.fasm`. Other lines are ignored. Returns the number of loaded truth tables.
+    ///
+    /// # Errors
+    /// `ParseError`: if an `INIT` line is malformed
+    /// `LutDoesNotExist`: if an `INIT` configures a LUT that is not part of the fabric
+    /// `LutInitTooWide`: if an `INIT` has more inputs than its LUT
+    pub fn load_inits(&mut self, fasm: &str) -> FabricResult<usize> {
+        let mut loaded = 0;
+        for (line_number, line) in fasm.lines().enumerate() {
+            let Some((tile, bel_index, init)) =
+                LutInit::from_fasm(line).map_err(|source| FabricError::ParseError { line_number, source })?
+            else {
+                continue;
+            };
+            let lut = self
+                .find_lut_mut(tile, bel_index)
+                .ok_or(FabricError::LutDoesNotExist { tile, bel_index })?;
+            if usize::from(init.inputs()) > lut.input_pin.len() {
+                return Err(FabricError::LutInitTooWide {
+                    tile,
+                    bel_index,
+                    inputs: init.inputs(),
+                    pins: lut.input_pin.len(),
+                });
+            }
+            lut.init = Some(init);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Sets the input permutation of every LUT from the `swaps` of a routed net-list against the
+    /// placed one, see [`crate::NetListExternal::swapped_inputs`]. LUTs without swaps keep their inputs.
+    ///
+    /// Inputs that were not connected fill the pins left by the moved inputs, so every
+    /// permutation stays complete.
+    ///
+    /// # Errors
+    /// `LutDoesNotExist`, `LutInputDoesNotExist`: if a swap is not between inputs of the same LUT
+    pub fn set_pin_permutations(&mut self, swaps: &[Swap]) -> FabricResult<()> {
+        let mut moves: BTreeMap<(TileId, char), Vec<(usize, usize)>> = BTreeMap::new();
+        for swap in swaps {
+            let (tile, bel_index) = (swap.sink_old.tile, lut_bel_index(&swap.sink_old.id));
+            let lut = self
+                .find_lut_mut(tile, bel_index)
+                .ok_or(FabricError::LutDoesNotExist { tile, bel_index })?;
+            let pin = |input: &str| {
+                lut.input_pin
+                    .iter()
+                    .position(|(pin, _)| pin == input)
+                    .ok_or_else(|| FabricError::LutInputDoesNotExist {
+                        tile,
+                        bel_index,
+                        input: input.to_string(),
+                    })
+            };
+            if swap.sink_new.tile != tile {
+                return Err(FabricError::LutInputDoesNotExist {
+                    tile,
+                    bel_index,
+                    input: swap.sink_new.id.clone(),
+                });
+            }
+            let (from, to) = (pin(&swap.sink_old.id)?, pin(&swap.sink_new.id)?);
+            moves.entry((tile, bel_index)).or_default().push((from, to));
+        }
+        for tile in self.0.values_mut() {
+            for lut in &mut tile.luts {
+                lut.permutation = [0, 1, 2, 3];
+            }
+        }
+        for ((tile, bel_index), moves) in moves {
+            if let Some(lut) = self.find_lut_mut(tile, bel_index) {
+                lut.permutation = complete_permutation(&moves);
+            }
+        }
+        Ok(())
+    }
+
+    /// Ties `input` of the LUT `bel_index` to the constant `state` by rewriting its truth table.
+    /// Returns `false` if no truth table was loaded for the LUT, the input is then left untouched.
+    ///
+    /// # Errors
+    /// `LutDoesNotExist`, `LutInputDoesNotExist`: if there is no such LUT input
+    pub fn fold_constant_input(&mut self, tile: TileId, bel_index: char, input: &str, state: State) -> FabricResult<bool> {
         let lut = self
             .find_lut_mut(tile, bel_index)
             .ok_or(FabricError::LutDoesNotExist { tile, bel_index })?;
-        let result = lut
+        let pin = lut
             .input_pin
-            .iter_mut()
-            .filter_map(|(a, b)| {
-                if b == &LutInputState::Used {
-                    return None;
-                }
-                *b = LutInputState::Used;
-                Some(a.clone())
+            .iter()
+            .position(|(pin, _)| pin == input)
+            .ok_or_else(|| FabricError::LutInputDoesNotExist {
+                tile,
+                bel_index,
+                input: input.to_string(),
+            })?;
+        let Some(init) = &mut lut.init else {
+            return Ok(false);
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let pin = pin as u8;
+        *init = init.cofactor(pin, state == State::High);
+        Ok(true)
+    }
+
+    /// FASM lines of the truth tables loaded by [`Self::load_inits`], permuted to the routed inputs,
+    /// and of the LUTs borrowed by [`Self::borrow_route_through`]
+    #[must_use]
+    pub fn generate_init_fasm(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .flat_map(|(tile_id, tile)| {
+                tile.luts.iter().filter_map(|lut| {
+                    lut.init
+                        .map(|init| init.permute(&lut.permutation[..usize::from(init.inputs())]).to_fasm(*tile_id, lut.bel_index))
+                })
             })
-            .collect();
-        Ok(result)
+            .collect()
+    }
+
+    /// Returns the free lut inputs of a specified lut without changing their state.
+    pub(crate) fn free_lut_inputs(&self, tile: TileId, bel_index: char) -> FabricResult<Vec<String>> {
+        let lut = self
+            .0
+            .get(&tile)
+            .and_then(|tile| tile.luts.iter().find(|lut| lut.bel_index == bel_index))
+            .ok_or(FabricError::LutDoesNotExist { tile, bel_index })?;
+        Ok(lut
+            .input_pin
+            .iter()
+            .filter(|(_, state)| state == &LutInputState::Free)
+            .map(|(input, _)| input.clone())
+            .collect())
+    }
+}
+
+/// Bel index of a LUT pin, e.g. `'A'` for `LA_I0`
+fn lut_bel_index(pin: &str) -> char {
+    pin.chars().nth(1).unwrap_or('?')
+}
+
+/// Permutation of 4 LUT inputs moving the inputs `from` to `to` for every move. The remaining
+/// inputs keep their pin if it is still free, otherwise they take the lowest free one.
+fn complete_permutation(moves: &[(usize, usize)]) -> [u8; 4] {
+    let mut permutation = [None; 4];
+    for (from, to) in moves {
+        permutation[*from] = Some(*to);
+    }
+    let taken = |permutation: &[Option<usize>; 4], pin: usize| permutation.contains(&Some(pin));
+    for input in 0..4 {
+        if permutation[input].is_none() && !taken(&permutation, input) {
+            permutation[input] = Some(input);
+        }
+    }
+    for input in 0..4 {
+        if permutation[input].is_none() {
+            let pin = (0..4).find(|pin| !taken(&permutation, *pin));
+            permutation[input] = pin;
+        }
     }
+    permutation.map(|pin| u8::try_from(pin.unwrap_or_default()).unwrap_or_default())
 }
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
-use crate::{FabricError, FabricResult, fabric::node::Node, netlist::NetListExternal};
+use crate::{FabricError, FabricResult, fabric::node::Node, netlist::NetListExternal, route_through::is_route_through};
 
 pub fn net_to_fasm(expanded_nets: &NetListExternal) -> FabricResult<String> {
     let mut fasm_output = Vec::new();
@@ -11,7 +11,7 @@ pub fn net_to_fasm(expanded_nets: &NetListExternal) -> FabricResult<String> {
         let mut net_lines = Vec::new();
         net_lines.push(format!("# Net {}", net.signal));
 
-        let mut unique_segments = HashSet::new();
+        let mut unique_segments = BTreeSet::new();
         for path in result.paths.values() {
             for pair in path.windows(2) {
                 if let Some(line) = nodes_to_fasm_line(&pair[0], &pair[1]) {
@@ -31,7 +31,8 @@ pub fn net_to_fasm(expanded_nets: &NetListExternal) -> FabricResult<String> {
 /// Helper: Extracts ``TILE.WIRE_IN.WIRE_OUT`` from two node IDs
 fn nodes_to_fasm_line(node_a: &Node, node_b: &Node) -> Option<String> {
     // u_parts[0] = Wire Name, u_parts[1] = Coordinate (X1Y1)
-    if node_a.tile == node_b.tile {
+    // A route-through is configured by the INIT of the LUT, not by a pip
+    if node_a.tile == node_b.tile && !is_route_through(node_a, node_b) {
         Some(format!("{}.{}.{}", node_a.tile, node_a.id, node_b.id))
     } else {
         None
@@ -0,0 +1,446 @@
+//! Module `generator`
+//!
+//! Creates synthetic net-lists for a `FabricGraph`. All random decisions are drawn from a
+//! generator seeded with [`TestConfig::seed`], so the same configuration always produces the
+//! same net-list.
+//!
+//! [`generate_sequential_test`] additionally places registers on LUT outputs, which creates
+//! designs with register-to-register paths for timing analysis.
+
+use std::collections::HashMap;
+
+use rand::{
+    Rng, SeedableRng,
+    distr::{Distribution, weighted::WeightedIndex},
+    rngs::StdRng,
+    seq::{IndexedRandom, SliceRandom},
+};
+
+use crate::{
+    FabricError, FabricGraph, FabricResult, NetExternal, NetInternal, NetListExternal,
+    fabric::{
+        graph::bucket_luts,
+        node::{NodeId, NodeType, TileId},
+    },
+    lut::LutInit,
+};
+
+/// How sinks are chosen relative to the signal of a net
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locality {
+    /// Every free LUT input is equally likely
+    Uniform,
+    /// Only LUT inputs at most this many tiles (Manhattan distance) away from the signal are used
+    MaxRadius(u8),
+    /// Rent's-rule style locality. A LUT input at Manhattan distance `d` is chosen with a weight
+    /// of `(1 + d)^-exponent`, so most connections stay local while some span the fabric.
+    Rent { exponent: f32 },
+}
+
+/// Number of sinks per net
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fanout {
+    /// Every net has exactly this many sinks
+    Fixed(usize),
+    /// Fanouts drawn from weighted `(fanout, weight)` pairs
+    Mixed(Vec<(usize, f32)>),
+}
+
+impl Fanout {
+    /// A distribution close to synthesized designs: mostly low fanout nets and a few large ones.
+    /// Fanouts above `max` are dropped.
+    #[must_use]
+    pub fn mixed(max: usize) -> Self {
+        let weights = [(1, 0.45), (2, 0.22), (3, 0.12), (4, 0.08), (6, 0.06), (8, 0.04), (12, 0.02), (16, 0.01)]
+            .into_iter()
+            .filter(|(fanout, _)| *fanout <= max)
+            .collect();
+        Self::Mixed(weights)
+    }
+}
+
+/// Parameters for [`generate_test`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestConfig {
+    /// Seed of the random number generator
+    pub seed: u64,
+    /// Share of all LUT outputs used as signal (0.0 - 1.0)
+    pub percentage: f32,
+    pub fanout: Fanout,
+    pub locality: Locality,
+    /// Number of nets driven by `VCC0`
+    pub vcc_nets: usize,
+    /// Number of nets driven by `GND0`
+    pub gnd_nets: usize,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            percentage: 0.2,
+            fanout: Fanout::Fixed(1),
+            locality: Locality::Uniform,
+            vcc_nets: 0,
+            gnd_nets: 0,
+        }
+    }
+}
+
+/// Creates a net-list as described by `config`. Every LUT input is used by at most one net.
+///
+/// # Errors
+/// Fails if parameters are bad like trying to use more than 100% of Lut-Outputs or when
+/// there are not enough LUT inputs for the requested fanout.
+pub fn generate_test(graph: &FabricGraph, config: &TestConfig) -> FabricResult<NetListExternal> {
+    validate_config(config)?;
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let (mut inputs, mut outputs) = bucket_luts(graph);
+    inputs.shuffle(&mut rng);
+    outputs.shuffle(&mut rng);
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+    let signal_count = (config.percentage * outputs.len() as f32) as usize;
+    let fanouts = draw_fanouts(&config.fanout, signal_count, &mut rng)?;
+    if fanouts.iter().sum::<usize>() > inputs.len() {
+        return Err(FabricError::CreatingTestBadParameters(
+            "Too many resulting input luts for given parameters.".to_string(),
+        ));
+    }
+
+    let mut plan = Vec::with_capacity(signal_count + config.vcc_nets + config.gnd_nets);
+    for (&signal, fanout) in outputs.iter().zip(fanouts) {
+        let sinks = choose_sinks(graph, signal, &mut inputs, fanout, config.locality, &mut rng)?;
+        if !sinks.is_empty() {
+            plan.push(to_external(graph, signal, sinks));
+        }
+    }
+    for constant in ["VCC0", "GND0"] {
+        let count = if constant == "VCC0" { config.vcc_nets } else { config.gnd_nets };
+        plan.extend(
+            constant_nets(graph, constant, count, &mut inputs, &config.fanout, &mut rng)?
+                .into_iter()
+                .map(|(signal, sinks)| to_external(graph, signal, sinks)),
+        );
+    }
+
+    Ok(NetListExternal {
+        plan,
+        hash: Some(graph.calculate_structure_hash()),
+        unroutable: Vec::new(),
+    })
+}
+
+fn validate_config(config: &TestConfig) -> FabricResult<()> {
+    if !(0.0..=1.0).contains(&config.percentage) {
+        return Err(FabricError::CreatingTestBadParameters(
+            "percentage needs to be between 0-100%".to_string(),
+        ));
+    }
+    let valid_fanout = match &config.fanout {
+        Fanout::Fixed(fanout) => *fanout > 0,
+        Fanout::Mixed(weights) => {
+            !weights.is_empty() && weights.iter().all(|(fanout, weight)| *fanout > 0 && *weight >= 0.0)
+        }
+    };
+    if !valid_fanout {
+        return Err(FabricError::CreatingTestBadParameters(
+            "Destinations cannot be 0.".to_string(),
+        ));
+    }
+    if let Locality::Rent { exponent } = config.locality
+        && !(exponent.is_finite() && exponent >= 0.0)
+    {
+        return Err(FabricError::CreatingTestBadParameters(
+            "The Rent exponent needs to be a positive number.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn draw_fanouts<R: Rng>(fanout: &Fanout, count: usize, rng: &mut R) -> FabricResult<Vec<usize>> {
+    match fanout {
+        Fanout::Fixed(fanout) => Ok(vec![*fanout; count]),
+        Fanout::Mixed(weights) => {
+            let distribution = WeightedIndex::new(weights.iter().map(|(_, weight)| *weight))
+                .map_err(|e| FabricError::CreatingTestBadParameters(format!("Invalid fanout weights: {e}")))?;
+            Ok((0..count).map(|_| weights[distribution.sample(rng)].0).collect())
+        }
+    }
+}
+
+const fn manhattan(a: TileId, b: TileId) -> u8 {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+/// Picks up to `fanout` sinks for `signal` from `inputs` and removes them from `inputs`.
+fn choose_sinks<R: Rng>(
+    graph: &FabricGraph,
+    signal: NodeId,
+    inputs: &mut Vec<NodeId>,
+    fanout: usize,
+    locality: Locality,
+    rng: &mut R,
+) -> FabricResult<Vec<NodeId>> {
+    let signal_tile = graph.get_node(signal).tile;
+    let distance = |input: &NodeId| manhattan(signal_tile, graph.get_node(*input).tile);
+
+    let chosen = match locality {
+        // `inputs` is shuffled, taking the first ones is a uniform choice.
+        Locality::Uniform => inputs.iter().take(fanout).copied().collect::<Vec<NodeId>>(),
+        Locality::MaxRadius(radius) => inputs
+            .iter()
+            .filter(|input| distance(input) <= radius)
+            .take(fanout)
+            .copied()
+            .collect(),
+        Locality::Rent { exponent } => inputs
+            .choose_multiple_weighted(rng, fanout, |input| (1.0 + f64::from(distance(input))).powf(-f64::from(exponent)))
+            .map_err(|e| FabricError::CreatingTestBadParameters(format!("Cannot choose sinks: {e}")))?
+            .copied()
+            .collect(),
+    };
+    inputs.retain(|input| !chosen.contains(input));
+    Ok(chosen)
+}
+
+/// Creates `count` nets driven by the constant node `constant` (`VCC0` or `GND0`).
+/// Like in placed designs each constant net only drives LUT inputs of the tile of its driver.
+fn constant_nets<R: Rng>(
+    graph: &FabricGraph,
+    constant: &str,
+    count: usize,
+    inputs: &mut Vec<NodeId>,
+    fanout: &Fanout,
+    rng: &mut R,
+) -> FabricResult<Vec<(NodeId, Vec<NodeId>)>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let drivers = graph
+        .nodes
+        .iter()
+        .filter(|node| node.id == constant)
+        .filter(|node| inputs.iter().any(|input| graph.get_node(*input).tile == node.tile))
+        .filter_map(|node| graph.get_node_id(&node.id()).copied())
+        .collect::<Vec<NodeId>>();
+    if drivers.len() < count {
+        return Err(FabricError::CreatingTestBadParameters(format!(
+            "Requested {count} {constant} nets but the fabric only has {} {constant} drivers.",
+            drivers.len()
+        )));
+    }
+
+    let fanouts = draw_fanouts(fanout, count, rng)?;
+    let mut nets = Vec::with_capacity(count);
+    for (&signal, fanout) in drivers.choose_multiple(rng, count).zip(fanouts) {
+        let tile = graph.get_node(signal).tile;
+        let sinks = inputs
+            .iter()
+            .filter(|input| {
+                let node = graph.get_node(**input);
+                node.tile == tile && matches!(node.typ, NodeType::LutInput(_))
+            })
+            .take(fanout)
+            .copied()
+            .collect::<Vec<NodeId>>();
+        inputs.retain(|input| !sinks.contains(input));
+        if !sinks.is_empty() {
+            nets.push((signal, sinks));
+        }
+    }
+    Ok(nets)
+}
+
+fn to_external(graph: &FabricGraph, signal: NodeId, sinks: Vec<NodeId>) -> NetExternal {
+    NetInternal {
+        sinks,
+        signal,
+        result: None,
+        intermediate_nodes: None,
+        bounding_box: None,
+    }
+    .to_external(graph)
+}
+
+/// Parameters for [`generate_sequential_test`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequentialConfig {
+    /// Seed of the random number generator
+    pub seed: u64,
+    /// Number of register-to-register chains
+    pub chains: usize,
+    /// Combinational LUTs between the launching and the capturing register of a chain
+    pub logic_depth: usize,
+    /// Locality of each connection relative to the LUT driving it
+    pub locality: Locality,
+}
+
+impl Default for SequentialConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            chains: 4,
+            logic_depth: 2,
+            locality: Locality::Uniform,
+        }
+    }
+}
+
+/// A LUT whose output is registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Register {
+    pub tile: TileId,
+    pub bel_index: char,
+}
+
+/// Net-list and LUT configuration created by [`generate_sequential_test`]
+#[derive(Debug, Clone)]
+pub struct SequentialTest {
+    pub net_list: NetListExternal,
+    /// Launching and capturing register of every chain
+    pub registers: Vec<Register>,
+    /// Truth tables of all LUTs driven by a chain. Each of them forwards its used input.
+    pub inits: Vec<(Register, LutInit)>,
+}
+
+impl SequentialTest {
+    /// FASM lines configuring the registers and LUT truth tables, in the same format as
+    /// the `ffs.fasm` file created from a placed design.
+    #[must_use]
+    pub fn fasm(&self) -> String {
+        let registers = self
+            .registers
+            .iter()
+            .map(|register| format!("{}.{}.FF", register.tile, register.bel_index));
+        let inits = self
+            .inits
+            .iter()
+            .map(|(lut, init)| init.to_fasm(lut.tile, lut.bel_index));
+        registers.chain(inits).collect::<Vec<String>>().join("\n")
+    }
+}
+
+/// Creates `config.chains` register-to-register chains.
+///
+/// Every chain starts at a registered LUT, passes through `config.logic_depth` combinational
+/// LUTs and ends on the input of another registered LUT. Each LUT is used by at most one chain
+/// and only one input of every LUT is connected.
+///
+/// # Errors
+/// Fails if `config.chains` is 0 or the fabric has not enough free LUTs for the chains.
+pub fn generate_sequential_test(graph: &FabricGraph, config: &SequentialConfig) -> FabricResult<SequentialTest> {
+    validate_config(&TestConfig {
+        locality: config.locality,
+        ..TestConfig::default()
+    })?;
+    if config.chains == 0 {
+        return Err(FabricError::CreatingTestBadParameters(
+            "At least one chain is needed.".to_string(),
+        ));
+    }
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let (mut inputs, mut outputs) = bucket_luts(graph);
+    if config.chains * (config.logic_depth + 2) > outputs.len() {
+        return Err(FabricError::CreatingTestBadParameters(format!(
+            "{} chains with a logic depth of {} need more LUTs than the fabric has.",
+            config.chains, config.logic_depth
+        )));
+    }
+    inputs.shuffle(&mut rng);
+    outputs.shuffle(&mut rng);
+    let lut_sizes = lut_sizes(graph, &inputs);
+
+    let mut plan = Vec::with_capacity(config.chains * (config.logic_depth + 1));
+    let mut registers = Vec::with_capacity(config.chains * 2);
+    let mut inits = Vec::with_capacity(config.chains * (config.logic_depth + 1));
+    for _ in 0..config.chains {
+        let Some(mut signal) = outputs.pop() else {
+            return Err(not_enough_luts(config));
+        };
+        let launch = lut_of(graph, signal);
+        claim_lut(graph, launch, &mut inputs, &mut outputs);
+        registers.push(launch);
+
+        for stage in 0..=config.logic_depth {
+            let sinks = choose_sinks(graph, signal, &mut inputs, 1, config.locality, &mut rng)?;
+            let Some(&sink) = sinks.first() else {
+                return Err(not_enough_luts(config));
+            };
+            let lut = lut_of(graph, sink);
+            claim_lut(graph, lut, &mut inputs, &mut outputs);
+            inits.push((lut, LutInit::identity(lut_sizes[&lut], input_index(graph, sink))));
+            plan.push(to_external(graph, signal, sinks));
+
+            if stage == config.logic_depth {
+                registers.push(lut);
+            } else {
+                signal = *graph
+                    .get_node_id(&format!("{}.L{}_O", lut.tile, lut.bel_index))
+                    .ok_or_else(|| {
+                        FabricError::CreatingTestBadParameters(format!(
+                            "LUT {} of tile {} has no output.",
+                            lut.bel_index, lut.tile
+                        ))
+                    })?;
+            }
+        }
+    }
+
+    Ok(SequentialTest {
+        net_list: NetListExternal {
+            plan,
+            hash: Some(graph.calculate_structure_hash()),
+            unroutable: Vec::new(),
+        },
+        registers,
+        inits,
+    })
+}
+
+fn not_enough_luts(config: &SequentialConfig) -> FabricError {
+    FabricError::CreatingTestBadParameters(format!(
+        "Not enough free LUTs for {} chains with a logic depth of {} and locality {:?}.",
+        config.chains, config.logic_depth, config.locality
+    ))
+}
+
+/// LUT of a LUT input or output node
+fn lut_of(graph: &FabricGraph, node: NodeId) -> Register {
+    let node = graph.get_node(node);
+    let bel_index = match node.typ {
+        NodeType::LutInput(bel_index) | NodeType::LutOutput(bel_index) => bel_index,
+        NodeType::Other => unreachable!("bucket_luts only returns LUT pins"),
+    };
+    Register {
+        tile: node.tile,
+        bel_index,
+    }
+}
+
+/// Index of a LUT input node, e.g. 2 for `LA_I2`
+fn input_index(graph: &FabricGraph, node: NodeId) -> u8 {
+    graph
+        .get_node(node)
+        .id
+        .chars()
+        .nth(4)
+        .and_then(|index| index.to_digit(10))
+        .and_then(|index| u8::try_from(index).ok())
+        .unwrap_or(0)
+}
+
+/// Number of inputs of every LUT
+fn lut_sizes(graph: &FabricGraph, inputs: &[NodeId]) -> HashMap<Register, u8> {
+    let mut sizes = HashMap::new();
+    for input in inputs {
+        *sizes.entry(lut_of(graph, *input)).or_insert(0) += 1;
+    }
+    sizes
+}
+
+/// Removes all pins of `lut` so no other net uses the LUT
+fn claim_lut(graph: &FabricGraph, lut: Register, inputs: &mut Vec<NodeId>, outputs: &mut Vec<NodeId>) {
+    inputs.retain(|input| lut_of(graph, *input) != lut);
+    outputs.retain(|output| lut_of(graph, *output) != lut);
+}
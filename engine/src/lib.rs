@@ -8,12 +8,21 @@
 //! as well as algorithms for finding and validating optimal routes.  
 
 pub(crate) mod api;
+pub(crate) mod constants;
+pub(crate) mod convergence;
+pub(crate) mod cost_model;
 pub(crate) mod dijkstra;
 pub(crate) mod error;
 pub(crate) mod fasm;
 pub(crate) mod fabric;
+pub(crate) mod generator;
+pub(crate) mod lookahead;
+pub(crate) mod lut;
+pub(crate) mod net_ordering;
 pub(crate) mod netlist;
 pub(crate) mod path_finder;
+pub(crate) mod routability;
+pub(crate) mod route_through;
 pub(crate) mod slack;
 pub(crate) mod solver;
 pub(crate) mod validate;
@@ -23,15 +32,35 @@ pub use error::{FabricError, FabricResult};
 
 // Public API
 pub use api::*;
+pub use constants::{DEFAULT_CONSTANT_SEARCH_RADIUS, find_constant_driver};
+pub use convergence::{CancellationToken, StagnationResponse, Stagnation, StopCriteria, StopReason};
+pub use cost_model::{CostModel, LegacyCostModel, VprCostModel};
+pub use dijkstra::{Heuristic, SearchOptions, SearchWorkspace};
+pub use fabric::adjacency::Adjacency;
+pub use fabric::bounding_box::BoundingBox;
 pub use fabric::graph::{FabricGraph, Fabric};
+pub use fabric::node::Costs;
 pub use fabric::tile_manager::TileManager;
 pub use fabric::parser::TimingModel;
-pub use netlist::{NetExternal, NetInternal, NetListExternal, NetListInternal, NetResultExternal, NetResultInternal, Swap};
-pub use path_finder::{CongestionReportExtern, IterationResult, TimingAnalysis};
+pub use generator::{
+    Fanout, Locality, Register, SequentialConfig, SequentialTest, TestConfig, generate_sequential_test, generate_test,
+};
+pub use lookahead::{LookaheadEntry, LookaheadTable, PinClass, wire_type};
+pub use lut::{LutInit, strip_lut_inits};
+pub use net_ordering::NetOrdering;
+pub use netlist::{
+    NetDiff, NetExternal, NetInternal, NetListDiff, NetListExternal, NetListInternal, NetResultExternal,
+    NetResultInternal, PathDiff, Swap, UnroutableSinkExternal, UnroutableSinkInternal,
+};
+pub use path_finder::{CongestionReportExtern, IterationResult, PartialRouting, RipUp, TimingAnalysis};
+pub use routability::{
+    CutDirection, CutLine, NetStatistics, RoutabilityReport, RoutabilityWarning, TilePinUsage, analyze_routability,
+};
+pub use route_through::DEFAULT_ROUTE_THROUGH_COST;
 pub use slack::SlackReport;
 
 use serde::Serialize;
-pub use solver::{RouteNet, SimpleSolver, SimpleSteinerSolver, SteinerSolver};
+pub use solver::{RouteNet, RouteTreeSolver, SimpleSolver, SimpleSteinerSolver, SteinerSolver};
 
 use crate::path_finder::StaIterationResult;
 
@@ -0,0 +1,303 @@
+//! Module `lookahead`
+//!
+//! VPR style routing lookahead. The table stores the cheapest costs and delay from a wire type
+//! to a target pin class at a tile offset `(dx, dy)`. It is computed once per fabric by running
+//! reverse searches from sampled target pins and guides [`Heuristic::Lookahead`] more
+//! tightly than the Manhattan lower bound. The entries are lower bounds for the sampled pins
+//! only, other pins at the same offset may be cheaper to reach.
+//!
+//! [`Heuristic::Lookahead`]: crate::Heuristic::Lookahead
+
+use std::{fs, path::Path};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    FabricError, FabricGraph, FabricResult,
+    fabric::node::{Node, NodeId, NodeType, TileId},
+};
+
+/// Kind of pin a search ends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PinClass {
+    LutInput,
+    /// Every other pin, e.g. `EN`, `SR` or routing wires
+    Other,
+}
+
+impl PinClass {
+    const ALL: [Self; 2] = [Self::LutInput, Self::Other];
+
+    #[must_use]
+    pub const fn of(node: &Node) -> Self {
+        match node.typ {
+            NodeType::LutInput(_) => Self::LutInput,
+            NodeType::LutOutput(_) | NodeType::Other => Self::Other,
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            Self::LutInput => 0,
+            Self::Other => 1,
+        }
+    }
+}
+
+/// Wire type of a node, its name without the trailing index, e.g. `N2BEG` for `N2BEG3`
+#[must_use]
+pub fn wire_type(node: &Node) -> &str {
+    node.id.trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+/// Cheapest way to reach a pin class at a certain offset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LookaheadEntry {
+    /// Congestion costs of the cheapest path on an unused fabric
+    pub cost: f32,
+    /// Sum of the base costs of the fastest path
+    pub delay: f32,
+}
+
+/// Expected costs and delay from a wire type to a pin class at a tile offset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LookaheadTable {
+    /// Structure hash of the `FabricGraph` the table was computed for
+    pub hash: String,
+    pub width: u8,
+    pub height: u8,
+    /// Sorted names of the wire types, see [`wire_type`]
+    wire_types: Vec<String>,
+    /// One grid per wire type and `PinClass` with `(2 * width - 1) * (2 * height - 1)` offsets
+    entries: Vec<Vec<Option<LookaheadEntry>>>,
+    /// Index into `wire_types` of every node of the graph, set by [`FabricGraph::set_lookahead`]
+    #[serde(skip)]
+    node_types: Vec<Option<usize>>,
+}
+
+impl LookaheadTable {
+    /// Number of target pins sampled per tile and pin class
+    pub const SAMPLES_PER_TILE: usize = 4;
+
+    /// Computes the table by running reverse searches from pins in the tiles closest to the
+    /// corners and the center of the fabric. Every node reached by a search updates the entry of
+    /// its wire type. The graph should not carry any usage or historic costs, otherwise the table
+    /// overestimates the costs.
+    ///
+    /// The table holds a grid of all tile offsets for every wire type and pin class, its size
+    /// grows with the number of wire types times the area of the fabric.
+    #[must_use]
+    pub fn compute(graph: &FabricGraph) -> Self {
+        let (width, height) = graph
+            .nodes
+            .iter()
+            .fold((0, 0), |(width, height), node| (width.max(node.tile.0 + 1), height.max(node.tile.1 + 1)));
+        let size = (2 * usize::from(width)).saturating_sub(1) * (2 * usize::from(height)).saturating_sub(1);
+        let mut wire_types = graph.nodes.iter().map(|node| wire_type(node).to_string()).collect::<Vec<String>>();
+        wire_types.sort_unstable();
+        wire_types.dedup();
+        let mut table = Self {
+            hash: graph.calculate_structure_hash(),
+            width,
+            height,
+            entries: vec![vec![None; size]; wire_types.len() * PinClass::ALL.len()],
+            wire_types,
+            node_types: Vec::new(),
+        };
+        table.node_types = table.node_types(graph);
+
+        let targets = PinClass::ALL
+            .iter()
+            .flat_map(|class| sample_targets(graph, *class))
+            .collect::<Vec<NodeId>>();
+        let searches = targets
+            .par_iter()
+            .map(|target| (*target, graph.reverse_costs(&[*target], 0.0), graph.reverse_costs(&[*target], 1.0)))
+            .collect::<Vec<(NodeId, Vec<f32>, Vec<f32>)>>();
+
+        for (target, costs, delays) in searches {
+            let target_node = graph.get_node(target);
+            let class = PinClass::of(target_node);
+            for (index, node) in graph.nodes.iter().enumerate() {
+                let (cost, delay) = (costs[index], delays[index]);
+                if !(cost < f32::MAX && delay < f32::MAX) || node == target_node {
+                    continue;
+                }
+                let (Some(grid), Some(slot)) = (table.grid(NodeId::new(index), class), table.index(node.tile, target_node.tile))
+                else {
+                    continue;
+                };
+                let entry = table.entries[grid][slot].get_or_insert(LookaheadEntry { cost, delay });
+                entry.cost = entry.cost.min(cost);
+                entry.delay = entry.delay.min(delay);
+            }
+        }
+        table
+    }
+
+    /// Index into `wire_types` of every node of `graph`
+    fn node_types(&self, graph: &FabricGraph) -> Vec<Option<usize>> {
+        graph
+            .nodes
+            .iter()
+            .map(|node| self.wire_types.binary_search_by(|name| name.as_str().cmp(wire_type(node))).ok())
+            .collect()
+    }
+
+    /// Grid of the wire type of `node` and `class`
+    fn grid(&self, node: NodeId, class: PinClass) -> Option<usize> {
+        self.node_types
+            .get(node.index())
+            .copied()
+            .flatten()
+            .map(|wire_type| wire_type * PinClass::ALL.len() + class.index())
+    }
+
+    fn index(&self, from: TileId, to: TileId) -> Option<usize> {
+        if from.0 >= self.width || to.0 >= self.width || from.1 >= self.height || to.1 >= self.height {
+            return None;
+        }
+        let dx = usize::from(to.0) + usize::from(self.width) - 1 - usize::from(from.0);
+        let dy = usize::from(to.1) + usize::from(self.height) - 1 - usize::from(from.1);
+        Some(dx * (2 * usize::from(self.height) - 1) + dy)
+    }
+
+    /// Entry for reaching a pin of `class` in tile `to` from a wire of type `wire_type` in tile `from`
+    #[must_use]
+    pub fn get(&self, wire_type: &str, from: TileId, to: TileId, class: PinClass) -> Option<LookaheadEntry> {
+        let wire_type = self.wire_types.binary_search_by(|name| name.as_str().cmp(wire_type)).ok()?;
+        let grid = wire_type * PinClass::ALL.len() + class.index();
+        self.index(from, to).and_then(|index| self.entries[grid][index])
+    }
+
+    /// Expected costs of reaching `target` from `node` in tile `from` for the given criticallity.
+    /// A table read from a file returns `None` until it is attached to its graph with [`FabricGraph::set_lookahead`].
+    #[must_use]
+    pub fn estimate(&self, node: NodeId, from: TileId, target: &Node, criticallity: f32) -> Option<f32> {
+        let grid = self.grid(node, PinClass::of(target))?;
+        self.index(from, target.tile)
+            .and_then(|index| self.entries[grid][index])
+            .map(|entry| criticallity.mul_add(entry.delay, (1.0 - criticallity) * entry.cost))
+    }
+
+    /// Reads a table written by [`Self::to_file`]
+    ///
+    /// # Errors
+    /// Fails if the file cannot be read or is not a valid table
+    pub fn from_file<P: AsRef<Path>>(file: P) -> FabricResult<Self> {
+        let path_ref = file.as_ref();
+        let data = fs::read_to_string(path_ref).map_err(|e| FabricError::Io {
+            path: path_ref.to_path_buf(),
+            source: e,
+        })?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    /// Writes the table as JSON
+    ///
+    /// # Errors
+    /// Fails if the file cannot be written
+    pub fn to_file<P: AsRef<Path>>(&self, file: P) -> FabricResult<()> {
+        let path_ref = file.as_ref();
+        fs::write(path_ref, serde_json::to_string(self)?).map_err(|e| FabricError::Io {
+            path: path_ref.to_path_buf(),
+            source: e,
+        })
+    }
+}
+
+impl FabricGraph {
+    /// Attaches a lookahead table used by [`Heuristic::Lookahead`](crate::Heuristic::Lookahead)
+    ///
+    /// # Errors
+    /// Fails if the table was computed for a different graph
+    pub fn set_lookahead(&mut self, mut table: LookaheadTable) -> FabricResult<()> {
+        if table.hash != self.calculate_structure_hash() {
+            return Err(FabricError::LookaheadGraphMismatch);
+        }
+        table.node_types = table.node_types(self);
+        self.lookahead = Some(table);
+        Ok(())
+    }
+}
+
+/// Pins of `class` in the tiles closest to the corners and the center of the fabric
+fn sample_targets(graph: &FabricGraph, class: PinClass) -> Vec<NodeId> {
+    let mut tiles = graph
+        .nodes
+        .iter()
+        .filter(|node| PinClass::of(node) == class)
+        .map(|node| (i16::from(node.tile.0), i16::from(node.tile.1)))
+        .collect::<Vec<(i16, i16)>>();
+    tiles.sort_unstable();
+    tiles.dedup();
+    let (Some(max_x), Some(max_y)) = (tiles.iter().map(|tile| tile.0).max(), tiles.iter().map(|tile| tile.1).max()) else {
+        return Vec::new();
+    };
+    let points = [(0, 0), (max_x, 0), (0, max_y), (max_x, max_y), (max_x / 2, max_y / 2)];
+    let mut sampled = points
+        .iter()
+        .filter_map(|(px, py)| tiles.iter().min_by_key(|(x, y)| (x - px).abs() + (y - py).abs()).copied())
+        .collect::<Vec<(i16, i16)>>();
+    sampled.sort_unstable();
+    sampled.dedup();
+
+    sampled
+        .into_iter()
+        .flat_map(|(x, y)| {
+            graph
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(move |(_, node)| {
+                    PinClass::of(node) == class && (i16::from(node.tile.0), i16::from(node.tile.1)) == (x, y)
+                })
+                .take(LookaheadTable::SAMPLES_PER_TILE)
+                .map(|(index, _)| NodeId::new(index))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use testing_utils::get_test_data_path;
+
+    #[test]
+    fn test_lookahead_is_lower_bound_for_samples() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let table = LookaheadTable::compute(&graph);
+        let mut checked = 0;
+        // The entries hold the cheapest costs and delay separately, so their blend bounds every criticallity
+        let targets = sample_targets(&graph, PinClass::LutInput).into_iter().step_by(5);
+        for (target, criticallity) in targets.zip([0.0, 0.5].into_iter().cycle()) {
+            let target_node = graph.get_node(target);
+            let costs = graph.reverse_costs(&[target], criticallity);
+            // Forward searches from a spread of the nodes reaching the target
+            let step = costs.iter().filter(|cost| **cost < f32::MAX).count() / 25 + 1;
+            let reaching = graph.nodes.iter().enumerate().filter(|(index, _)| costs[*index] < f32::MAX);
+            for (index, node) in reaching.step_by(step) {
+                let Some(estimate) = table.estimate(NodeId::new(index), node.tile, target_node, criticallity) else {
+                    continue;
+                };
+                if NodeId::new(index) == target {
+                    continue;
+                }
+                let (_, cost) = graph.dijkstra(NodeId::new(index), target, criticallity).unwrap();
+                assert!(estimate <= cost + 1e-3, "{} -> {}: {estimate} > {cost}", node.id(), target_node.id());
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+        // Wires of different types get their own entries
+        let tile = graph.get_node(sample_targets(&graph, PinClass::LutInput)[0]).tile;
+        let entries = table
+            .wire_types
+            .iter()
+            .filter_map(|wire_type| table.get(wire_type, tile, tile, PinClass::LutInput))
+            .map(|entry| entry.cost.to_bits())
+            .collect::<std::collections::BTreeSet<u32>>();
+        assert!(entries.len() > 1);
+    }
+}
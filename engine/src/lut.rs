@@ -0,0 +1,209 @@
+//! Module `lut`
+//!
+//! Truth tables (`INIT` values) of LUTs and their FASM representation.
+
+use crate::fabric::{error::ParseError, node::TileId};
+
+/// Truth table of a LUT with up to 6 inputs.
+///
+/// Bit `i` of `bits` is the output for the input combination `i`, where input `k`
+/// contributes bit `k` of `i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LutInit {
+    bits: u64,
+    inputs: u8,
+}
+
+impl LutInit {
+    /// Largest supported number of LUT inputs
+    pub const MAX_INPUTS: u8 = 6;
+
+    /// Creates a truth table from its raw `bits`. Bits beyond `2^inputs` are cleared.
+    ///
+    /// # Panics
+    /// Panics if `inputs` is larger than [`Self::MAX_INPUTS`]
+    #[must_use]
+    pub const fn new(bits: u64, inputs: u8) -> Self {
+        assert!(inputs <= Self::MAX_INPUTS, "LUTs with more than 6 inputs are not supported");
+        Self {
+            bits: bits & Self::mask(inputs),
+            inputs,
+        }
+    }
+
+    /// Truth table that forwards `input` to the output
+    #[must_use]
+    pub const fn identity(inputs: u8, input: u8) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < Self::size(inputs) {
+            if (i >> input) & 1 == 1 {
+                bits |= 1 << i;
+            }
+            i += 1;
+        }
+        Self::new(bits, inputs)
+    }
+
+    #[must_use]
+    pub const fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    #[must_use]
+    pub const fn inputs(&self) -> u8 {
+        self.inputs
+    }
+
+    /// Number of entries of the truth table
+    const fn size(inputs: u8) -> usize {
+        1 << inputs
+    }
+
+    const fn mask(inputs: u8) -> u64 {
+        if inputs == Self::MAX_INPUTS {
+            u64::MAX
+        } else {
+            (1 << Self::size(inputs)) - 1
+        }
+    }
+
+    /// Truth table computing the same function after input `k` moved to input `permutation[k]`.
+    ///
+    /// # Panics
+    /// Panics if `permutation` does not have an entry for every input
+    #[must_use]
+    pub fn permute(&self, permutation: &[u8]) -> Self {
+        assert_eq!(permutation.len(), usize::from(self.inputs), "The permutation must cover every input");
+        let mut bits = 0;
+        for new_index in 0..Self::size(self.inputs) {
+            let old_index = permutation
+                .iter()
+                .enumerate()
+                .fold(0, |index, (k, pin)| index | (((new_index >> pin) & 1) << k));
+            bits |= ((self.bits >> old_index) & 1) << new_index;
+        }
+        Self::new(bits, self.inputs)
+    }
+
+    /// Truth table with `input` tied to the constant `value`. The result no longer depends on `input`.
+    #[must_use]
+    pub const fn cofactor(&self, input: u8, value: bool) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < Self::size(self.inputs) {
+            let source = if value { i | (1 << input) } else { i & !(1 << input) };
+            bits |= ((self.bits >> source) & 1) << i;
+            i += 1;
+        }
+        Self::new(bits, self.inputs)
+    }
+
+    /// Parses a FASM line configuring a truth table, e.g. `X1Y1.A.INIT[15:0] = 16'b1010101010101010`.
+    /// Binary and hexadecimal values are accepted. Returns `Ok(None)` for every other line.
+    ///
+    /// # Errors
+    /// Returns [`ParseError::InvalidInit`] if the line configures an INIT but cannot be parsed
+    pub fn from_fasm(line: &str) -> Result<Option<(TileId, char, Self)>, ParseError> {
+        let Some((feature, value)) = line.split_once('=') else {
+            return Ok(None);
+        };
+        let Some((lut, _range)) = feature.trim().split_once(".INIT[") else {
+            return Ok(None);
+        };
+        let invalid = || ParseError::InvalidInit { content: line.to_string() };
+        let (tile, bel_index) = lut.split_once('.').ok_or_else(invalid)?;
+        let tile = TileId::from_str_coords(tile)?;
+        let bel_index = bel_index.chars().next().ok_or_else(invalid)?;
+        let (size, digits) = value.trim().split_once('\'').ok_or_else(invalid)?;
+        let size = size.parse::<u32>().map_err(|_| invalid())?;
+        let radix = match digits.chars().next() {
+            Some('b') => 2,
+            Some('h') => 16,
+            _ => return Err(invalid()),
+        };
+        let bits = u64::from_str_radix(&digits[1..].replace('_', ""), radix).map_err(|_| invalid())?;
+        let inputs = u8::try_from(size.trailing_zeros()).map_err(|_| invalid())?;
+        if !size.is_power_of_two() || inputs > Self::MAX_INPUTS {
+            return Err(invalid());
+        }
+        Ok(Some((tile, bel_index, Self::new(bits, inputs))))
+    }
+
+    /// FASM line configuring the LUT `bel_index` of `tile`, e.g.
+    /// `X1Y1.A.INIT[15:0] = 16'b1010101010101010`
+    #[must_use]
+    pub fn to_fasm(&self, tile: TileId, bel_index: char) -> String {
+        let size = Self::size(self.inputs);
+        format!(
+            "{tile}.{bel_index}.INIT[{}:0] = {size}'b{:0size$b}",
+            size - 1,
+            self.bits
+        )
+    }
+}
+
+/// Removes the lines of `fasm` configuring a LUT truth table, e.g. to replace them with the INITs
+/// of [`crate::TileManager::generate_init_fasm`]
+#[must_use]
+pub fn strip_lut_inits(fasm: &str) -> String {
+    fasm.lines()
+        .filter(|line| !matches!(LutInit::from_fasm(line), Ok(Some(_))))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(LutInit::identity(4, 0).bits(), 0xAAAA);
+        assert_eq!(LutInit::identity(4, 1).bits(), 0xCCCC);
+        assert_eq!(LutInit::identity(4, 2).bits(), 0xF0F0);
+        assert_eq!(LutInit::identity(4, 3).bits(), 0xFF00);
+        assert_eq!(LutInit::identity(2, 1).bits(), 0b1100);
+    }
+
+    #[test]
+    fn test_to_fasm() {
+        let init = LutInit::identity(4, 0);
+        assert_eq!(init.to_fasm(TileId(1, 2), 'B'), "X1Y2.B.INIT[15:0] = 16'b1010101010101010");
+    }
+
+    #[test]
+    fn test_permute() {
+        // Input 0 moved to input 2, the LUT must now forward input 2
+        let init = LutInit::identity(4, 0).permute(&[2, 1, 0, 3]);
+        assert_eq!(init, LutInit::identity(4, 2));
+        // a & !b on inputs 0 and 1, swapped to b & !a
+        let init = LutInit::new(0b0010_0010_0010_0010, 4).permute(&[1, 0, 2, 3]);
+        assert_eq!(init.bits(), 0b0100_0100_0100_0100);
+        let identity = [0, 1, 2, 3];
+        assert_eq!(LutInit::new(0x1234, 4).permute(&identity).bits(), 0x1234);
+    }
+
+    #[test]
+    fn test_cofactor() {
+        let and = LutInit::new(0x8000, 4);
+        assert_eq!(and.cofactor(2, false).bits(), 0);
+        // The AND of the inputs 0, 1 and 3
+        assert_eq!(and.cofactor(2, true).bits(), 0x8800);
+        assert_eq!(LutInit::identity(4, 1).cofactor(1, true).bits(), 0xFFFF);
+        assert_eq!(LutInit::identity(4, 1).cofactor(0, true), LutInit::identity(4, 1));
+    }
+
+    #[test]
+    fn test_from_fasm() {
+        let line = LutInit::identity(4, 1).to_fasm(TileId(3, 4), 'C');
+        assert_eq!(LutInit::from_fasm(&line), Ok(Some((TileId(3, 4), 'C', LutInit::identity(4, 1)))));
+        assert_eq!(
+            LutInit::from_fasm("X1Y1.A.INIT[15:0] = 16'hFF00"),
+            Ok(Some((TileId(1, 1), 'A', LutInit::identity(4, 3))))
+        );
+        assert_eq!(LutInit::from_fasm("X1Y1.A.FF"), Ok(None));
+        assert!(LutInit::from_fasm("X1Y1.A.INIT[15:0] = 16'b102").is_err());
+        assert_eq!(strip_lut_inits("X1Y1.A.FF\nX1Y1.A.INIT[15:0] = 16'hFF00"), "X1Y1.A.FF");
+    }
+}
@@ -0,0 +1,176 @@
+//! Module `net_ordering`
+//!
+//! Order in which the path finder routes the nets of an iteration. Nets routed early see less
+//! congestion, so the order decides which nets get the cheapest wires.
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeSet,
+};
+
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Fabric,
+    fabric::node::NodeId,
+    netlist::NetInternal,
+};
+
+/// Strategy sorting the nets before every iteration. Ties keep the order of the signal ids.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+pub enum NetOrdering {
+    /// The order of the net-list after the pre-processing
+    #[default]
+    AsGiven,
+    /// Nets with more sinks first
+    Fanout,
+    /// Nets with a larger pin bounding box first
+    BoundingBox,
+    /// Nets with the most critical connection of the last `SlackReport` first
+    Criticality,
+    /// Nets using the most nodes that were overused in the previous iteration first
+    Congestion,
+    /// A new random order every iteration, drawn from `seed` and the iteration
+    Random { seed: u64 },
+}
+
+impl NetOrdering {
+    /// Sorts `nets` for `iteration`. `overused` are the overused nodes of the previous iteration.
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn sort(self, fabric: &Fabric, nets: &mut [NetInternal], iteration: usize, overused: Option<&BTreeSet<NodeId>>) {
+        match self {
+            Self::AsGiven => {}
+            Self::Fanout => sort_descending(nets, |net| net.sinks.len() as f32),
+            Self::BoundingBox => sort_descending(nets, |net| {
+                net.pin_bounding_box(&fabric.graph)
+                    .map_or(0.0, |bounding_box| bounding_box.half_perimeter() as f32)
+            }),
+            Self::Criticality => sort_descending(nets, |net| criticality(fabric, net)),
+            Self::Congestion => sort_descending(nets, |net| match (overused, &net.result) {
+                (Some(overused), Some(result)) => result.nodes.intersection(overused).count() as f32,
+                _ => 0.0,
+            }),
+            Self::Random { seed } => {
+                nets.sort_by_key(|net| net.signal);
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(iteration as u64));
+                nets.shuffle(&mut rng);
+            }
+        }
+    }
+}
+
+/// Sorts by descending `key`, ties are ordered by the signal. The key is computed once per net.
+fn sort_descending<F: Fn(&NetInternal) -> f32>(nets: &mut [NetInternal], key: F) {
+    nets.sort_by_cached_key(|net| (Reverse(TotalOrder(key(net))), net.signal));
+}
+
+/// `f32` ordered by [`f32::total_cmp`]
+struct TotalOrder(f32);
+
+impl PartialEq for TotalOrder {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for TotalOrder {}
+
+impl PartialOrd for TotalOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TotalOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Highest criticality of the connections of `net`
+fn criticality(fabric: &Fabric, net: &NetInternal) -> f32 {
+    fabric.slack_report.as_ref().map_or(0.0, |slack_report| {
+        net.sinks
+            .iter()
+            .filter_map(|sink| slack_report.criticalities.get(&(net.signal, *sink)))
+            .copied()
+            .max_by(f32::total_cmp)
+            .unwrap_or(0.0)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::*;
+    use crate::{FabricGraph, SlackReport, TileManager, netlist::NetResultInternal};
+    use testing_utils::get_test_data_path;
+
+    /// Three nets that every ordering sorts differently
+    fn nets(fabric: &Fabric) -> Vec<NetInternal> {
+        let id = |name: &str| *fabric.graph.get_node_id(name).unwrap();
+        [
+            ("X1Y1.J2MID_ABa_END0", vec!["X3Y2.LA_I0"]),
+            ("X2Y2.J2MID_ABa_END0", vec!["X1Y1.LC_I0", "X3Y3.LA_I0", "X3Y3.LB_I0"]),
+            ("X3Y3.J2MID_ABa_END0", vec!["X3Y3.LC_I0", "X2Y3.LA_I0"]),
+        ]
+        .into_iter()
+        .map(|(signal, sinks)| NetInternal {
+            signal: id(signal),
+            sinks: sinks.into_iter().map(id).collect(),
+            result: None,
+            intermediate_nodes: None,
+            bounding_box: None,
+        })
+        .collect()
+    }
+
+    fn sorted(ordering: NetOrdering, fabric: &Fabric, nets: &[NetInternal], overused: Option<&BTreeSet<NodeId>>) -> Vec<NodeId> {
+        let mut nets = nets.to_vec();
+        ordering.sort(fabric, &mut nets, 1, overused);
+        nets.iter().map(|net| net.signal).collect()
+    }
+
+    #[test]
+    fn test_sort_every_ordering() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let mut fabric = Fabric::new(graph, tile_manager);
+        let mut nets = nets(&fabric);
+        let signals = nets.iter().map(|net| net.signal).collect::<Vec<NodeId>>();
+        let order = |indices: [usize; 3]| indices.map(|index| signals[index]).to_vec();
+
+        assert_eq!(sorted(NetOrdering::AsGiven, &fabric, &nets, None), signals);
+        assert_eq!(sorted(NetOrdering::Fanout, &fabric, &nets, None), order([1, 2, 0]));
+        assert_eq!(sorted(NetOrdering::BoundingBox, &fabric, &nets, None), order([1, 0, 2]));
+
+        fabric.slack_report = Some(SlackReport {
+            slacks: HashMap::new(),
+            criticalities: HashMap::from([((nets[2].signal, nets[2].sinks[0]), 0.9), ((nets[0].signal, nets[0].sinks[0]), 0.5)]),
+            worst_slack: ((nets[2].signal, nets[2].sinks[0]), -1.0),
+        });
+        assert_eq!(sorted(NetOrdering::Criticality, &fabric, &nets, None), order([2, 0, 1]));
+
+        let overused = (0..3).map(NodeId::new).collect::<BTreeSet<NodeId>>();
+        for (net, used) in nets.iter_mut().zip([2, 1, 0]) {
+            net.result = Some(NetResultInternal {
+                paths: BTreeMap::new(),
+                nodes: (0..used).map(NodeId::new).collect(),
+            });
+        }
+        assert_eq!(sorted(NetOrdering::Congestion, &fabric, &nets, Some(&overused)), order([0, 1, 2]));
+        assert_eq!(sorted(NetOrdering::Congestion, &fabric, &nets, None), signals);
+
+        // The random order only depends on the seed and the iteration, not on the input order
+        let random = sorted(NetOrdering::Random { seed: 7 }, &fabric, &nets, None);
+        nets.reverse();
+        assert_eq!(sorted(NetOrdering::Random { seed: 7 }, &fabric, &nets, None), random);
+        let mut shuffled = random;
+        shuffled.sort_unstable();
+        let mut expected = signals.clone();
+        expected.sort_unstable();
+        assert_eq!(shuffled, expected);
+    }
+}
@@ -0,0 +1,210 @@
+//! Module `diff`
+//!
+//! Compares two routed `NetListExternal`s. Used to review the effect of solver or
+//! parameter changes on the same design.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{
+    NetExternal, NetListExternal, Swap,
+    fabric::node::{Node, NodeType, TileId},
+};
+
+/// Difference between two net-lists
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct NetListDiff {
+    /// Signals only present in the new net-list
+    pub added_nets: Vec<Node>,
+    /// Signals only present in the old net-list
+    pub removed_nets: Vec<Node>,
+    /// Nets present in both net-lists whose sinks or routing differ
+    pub changed_nets: Vec<NetDiff>,
+    /// Number of routing nodes used by the old net-list
+    pub old_wire_length: usize,
+    /// Number of routing nodes used by the new net-list
+    pub new_wire_length: usize,
+    /// Sum of the hops of all sink paths in the old net-list
+    pub old_hops: usize,
+    /// Sum of the hops of all sink paths in the new net-list
+    pub new_hops: usize,
+}
+
+/// Difference of a single net, identified by its signal
+#[derive(Debug, Clone, Serialize)]
+pub struct NetDiff {
+    pub signal: Node,
+    /// Sinks only present in the new net
+    pub added_sinks: Vec<Node>,
+    /// Sinks only present in the old net
+    pub removed_sinks: Vec<Node>,
+    /// Sinks present in both nets that were routed along a different path
+    pub changed_paths: Vec<PathDiff>,
+    /// Change in the number of routing nodes used by the net
+    pub wire_length_delta: i64,
+    /// Change in the sum of hops over all sink paths of the net
+    pub hops_delta: i64,
+}
+
+/// A sink that is routed differently in the new net-list
+#[derive(Debug, Clone, Serialize)]
+pub struct PathDiff {
+    pub sink: Node,
+    /// Hops of the old path, `None` if the sink was not routed
+    pub old_hops: Option<usize>,
+    /// Hops of the new path, `None` if the sink is not routed
+    pub new_hops: Option<usize>,
+}
+
+impl NetListDiff {
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub const fn wire_length_delta(&self) -> i64 {
+        self.new_wire_length as i64 - self.old_wire_length as i64
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub const fn hops_delta(&self) -> i64 {
+        self.new_hops as i64 - self.old_hops as i64
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added_nets.is_empty() && self.removed_nets.is_empty() && self.changed_nets.is_empty()
+    }
+
+    /// Pairs up removed and added sinks on the same LUT of every changed net.
+    /// A net whose sinks moved to equivalent LUT inputs shows up as one `Swap` per moved sink.
+    /// Sinks without a counterpart on their LUT are no swaps and skipped.
+    #[must_use]
+    pub fn swaps(&self) -> Vec<Swap> {
+        self.changed_nets
+            .iter()
+            .flat_map(|net| {
+                let mut added = by_lut(&net.added_sinks);
+                by_lut(&net.removed_sinks)
+                    .into_iter()
+                    .filter_map(move |(lut, removed)| added.remove(&lut).map(|added| (removed, added)))
+                    .flat_map(|(removed, added)| removed.into_iter().zip(added))
+                    .map(|(sink_old, sink_new)| Swap {
+                        signal: net.signal.clone(),
+                        sink_old: sink_old.clone(),
+                        sink_new: sink_new.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Groups the LUT input `sinks` by their tile and bel, other sinks are dropped
+fn by_lut(sinks: &[Node]) -> BTreeMap<(TileId, char), Vec<&Node>> {
+    let mut luts: BTreeMap<(TileId, char), Vec<&Node>> = BTreeMap::new();
+    for sink in sinks {
+        if let NodeType::LutInput(bel_index) = sink.typ {
+            luts.entry((sink.tile, bel_index)).or_default().push(sink);
+        }
+    }
+    luts
+}
+
+/// Sinks and routing of one signal. Nets that share a signal are merged.
+#[derive(Default)]
+struct NetSummary<'a> {
+    sinks: BTreeSet<&'a Node>,
+    paths: BTreeMap<&'a Node, &'a [Node]>,
+    nodes: BTreeSet<&'a Node>,
+}
+
+impl NetSummary<'_> {
+    fn hops(&self) -> usize {
+        self.paths.values().map(|path| path.len().saturating_sub(1)).sum()
+    }
+}
+
+fn summarize(plan: &[NetExternal]) -> BTreeMap<&Node, NetSummary<'_>> {
+    let mut nets: BTreeMap<&Node, NetSummary> = BTreeMap::new();
+    for net in plan {
+        let summary = nets.entry(&net.signal).or_default();
+        summary.sinks.extend(&net.sinks);
+        if let Some(result) = &net.result {
+            summary.nodes.extend(&result.nodes);
+            summary
+                .paths
+                .extend(result.paths.iter().map(|(sink, path)| (sink, path.as_slice())));
+        }
+    }
+    nets
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn diff_net(signal: &Node, old: &NetSummary, new: &NetSummary) -> Option<NetDiff> {
+    let added_sinks = new.sinks.difference(&old.sinks).map(|&a| a.clone()).collect::<Vec<Node>>();
+    let removed_sinks = old.sinks.difference(&new.sinks).map(|&a| a.clone()).collect::<Vec<Node>>();
+    let changed_paths = old
+        .sinks
+        .intersection(&new.sinks)
+        .filter_map(|&sink| {
+            let old_path = old.paths.get(sink);
+            let new_path = new.paths.get(sink);
+            if old_path == new_path {
+                return None;
+            }
+            Some(PathDiff {
+                sink: sink.clone(),
+                old_hops: old_path.map(|path| path.len().saturating_sub(1)),
+                new_hops: new_path.map(|path| path.len().saturating_sub(1)),
+            })
+        })
+        .collect::<Vec<PathDiff>>();
+
+    if added_sinks.is_empty() && removed_sinks.is_empty() && changed_paths.is_empty() {
+        return None;
+    }
+    Some(NetDiff {
+        signal: signal.clone(),
+        added_sinks,
+        removed_sinks,
+        changed_paths,
+        wire_length_delta: new.nodes.len() as i64 - old.nodes.len() as i64,
+        hops_delta: new.hops() as i64 - old.hops() as i64,
+    })
+}
+
+impl NetListExternal {
+    /// Compares `self` (the old net-list) with `new`.
+    ///
+    /// Nets are matched by their signal. Sinks that only exist in one of both nets are
+    /// reported as added or removed, sinks existing in both are compared by their path.
+    #[must_use]
+    pub fn diff(&self, new: &Self) -> NetListDiff {
+        let old_nets = summarize(&self.plan);
+        let new_nets = summarize(&new.plan);
+
+        let added_nets = new_nets
+            .keys()
+            .filter(|signal| !old_nets.contains_key(*signal))
+            .map(|&signal| signal.clone())
+            .collect();
+        let removed_nets = old_nets
+            .keys()
+            .filter(|signal| !new_nets.contains_key(*signal))
+            .map(|&signal| signal.clone())
+            .collect();
+        let changed_nets = old_nets
+            .iter()
+            .filter_map(|(signal, old)| new_nets.get(signal).and_then(|new| diff_net(signal, old, new)))
+            .collect();
+
+        NetListDiff {
+            added_nets,
+            removed_nets,
+            changed_nets,
+            old_wire_length: old_nets.values().map(|net| net.nodes.len()).sum(),
+            new_wire_length: new_nets.values().map(|net| net.nodes.len()).sum(),
+            old_hops: old_nets.values().map(NetSummary::hops).sum(),
+            new_hops: new_nets.values().map(NetSummary::hops).sum(),
+        }
+    }
+}
@@ -1,6 +1,8 @@
+mod diff;
 pub mod error;
 mod netlist_external;
 mod netlist_internal;
 
-pub use netlist_external::{NetExternal, NetListExternal, NetResultExternal, Swap};
-pub use netlist_internal::{NetInternal, NetListInternal, NetResultInternal};
+pub use diff::{NetDiff, NetListDiff, PathDiff};
+pub use netlist_external::{NetExternal, NetListExternal, NetResultExternal, Swap, UnroutableSinkExternal};
+pub use netlist_internal::{NetInternal, NetListInternal, NetResultInternal, UnroutableSinkInternal};
@@ -1,4 +1,4 @@
-use std::{collections::{HashMap, HashSet},  fs, path::Path};
+use std::{collections::{BTreeMap, BTreeSet},  fs, path::Path};
 
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +9,18 @@ use crate::{FabricError, FabricResult, fabric::node::Node};
 pub struct NetListExternal {
     pub hash: Option<String>,
     pub plan: Vec<NetExternal>,
+    /// Connections excluded from the routing because no path to their sink was found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unroutable: Vec<UnroutableSinkExternal>,
+}
+
+/// Connection from `signal` to `sink` that was excluded from the routing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnroutableSinkExternal {
+    pub signal: Node,
+    pub sink: Node,
+    /// Why no path to the sink was found
+    pub reason: String,
 }
 
 /// Represents a entry in the `NetList`
@@ -28,9 +40,9 @@ pub struct NetExternal {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetResultExternal {
     /// Paths from source to each sink
-    pub paths: HashMap<Node, Vec<Node>>,
+    pub paths: BTreeMap<Node, Vec<Node>>,
     /// All nodes used in the routing
-    pub nodes: HashSet<Node>,
+    pub nodes: BTreeSet<Node>,
 }
 
 impl NetListExternal {
@@ -49,24 +61,23 @@ impl NetListExternal {
         Ok(x)
     }
 
+    /// Returns the sinks that were moved to a different LUT input compared to `old`.
+    /// Sinks of `self.unroutable` are not counted as moved.
     #[must_use]
     pub fn swapped_inputs(&self, old: &Self) -> Vec<Swap> {
-        let mut x1 = self
-            .plan
-            .iter()
-            .flat_map(|net| net.sinks.iter().map(|a| (net.signal.clone(), a.clone())))
-            .collect::<Vec<(Node, Node)>>();
-        let mut x2 = old
-            .plan
-            .iter()
-            .flat_map(|net| net.sinks.iter().map(|a| (net.signal.clone(), a.clone())))
-            .collect::<Vec<(Node, Node)>>();
-        x1.sort();
-        x2.sort();
-        x1.into_iter()
-            .zip(x2)
-            .filter_map(|((signal, sink_old), (signal2, sink_new))| if signal == signal2 && sink_new != sink_old {Some(Swap {signal, sink_old, sink_new})} else { None })
-            .collect::<Vec<Swap>>()
+        if self.unroutable.is_empty() {
+            return old.diff(self).swaps();
+        }
+        let mut old = old.clone();
+        for net in &mut old.plan {
+            net.sinks.retain(|sink| {
+                !self
+                    .unroutable
+                    .iter()
+                    .any(|unroutable| unroutable.signal == net.signal && &unroutable.sink == sink)
+            });
+        }
+        old.diff(self).swaps()
     }
 }
 
@@ -1,13 +1,28 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use super::error::{MapExternalError, MapExternalResult};
 use crate::{
-    FabricGraph, NetExternal, NetListExternal, NetResultExternal,
-    fabric::node::{Node, NodeId},
+    FabricGraph, NetExternal, NetListExternal, NetResultExternal, UnroutableSinkExternal,
+    fabric::{
+        bounding_box::BoundingBox,
+        node::{Node, NodeId},
+    },
 };
 
+#[derive(Debug, Clone)]
 pub struct NetListInternal {
     pub plan: Vec<NetInternal>,
+    /// Connections excluded from the routing, see [`crate::RoutingConfigBuilder::skip_unroutable`]
+    pub unroutable: Vec<UnroutableSinkInternal>,
+}
+
+/// Connection from `signal` to `sink` that was excluded from the routing
+#[derive(Debug, Clone)]
+pub struct UnroutableSinkInternal {
+    pub signal: NodeId,
+    pub sink: NodeId,
+    /// Why no path to the sink was found
+    pub reason: String,
 }
 
 /// Routing request from a source to multiple sinks
@@ -20,15 +35,17 @@ pub struct NetInternal {
     /// Optional routing result after computation
     pub result: Option<NetResultInternal>,
     pub intermediate_nodes: Option<HashMap<NodeId, Vec<NodeId>>>,
+    /// Region the searches of this net are restricted to, `None` searches the whole fabric
+    pub bounding_box: Option<BoundingBox>,
 }
 
 /// Routing result for a routing request
 #[derive(Debug, Clone)]
 pub struct NetResultInternal {
     /// Paths from source to each sink
-    pub paths: HashMap<NodeId, Vec<NodeId>>,
+    pub paths: BTreeMap<NodeId, Vec<NodeId>>,
     /// All nodes used in the routing
-    pub nodes: HashSet<NodeId>,
+    pub nodes: BTreeSet<NodeId>,
 }
 
 impl NetListInternal {
@@ -47,17 +64,36 @@ impl NetListInternal {
                 })
             })
             .collect::<Result<Vec<NetInternal>, MapExternalError>>()?;
-        Ok(Self { plan: route_plan })
+        let unroutable = external
+            .unroutable
+            .iter()
+            .map(|unroutable| UnroutableSinkInternal::from_external(unroutable, graph))
+            .collect::<MapExternalResult<Vec<UnroutableSinkInternal>>>()?;
+        Ok(Self {
+            plan: route_plan,
+            unroutable,
+        })
     }
     #[must_use]
     pub fn to_external(&self, graph: &FabricGraph) -> NetListExternal {
         let plan = self.plan.iter().map(|x| x.to_external(graph)).collect::<Vec<_>>();
         let hash = Some(graph.calculate_structure_hash());
-        NetListExternal { hash, plan }
+        let unroutable = self.unroutable.iter().map(|x| x.to_external(graph)).collect();
+        NetListExternal { hash, plan, unroutable }
     }
 
 }
 impl NetInternal {
+    /// Smallest box containing the signal and all sinks
+    #[must_use]
+    pub fn pin_bounding_box(&self, graph: &FabricGraph) -> Option<BoundingBox> {
+        BoundingBox::from_tiles(
+            std::iter::once(self.signal)
+                .chain(self.sinks.iter().copied())
+                .map(|node| graph.get_node(node).tile),
+        )
+    }
+
     /// Transforms a `NetExternal` to a `Self` by mapping the name ids to internal used ids
     /// # Errors
     /// Fails if mapping is not possible
@@ -88,6 +124,7 @@ impl NetInternal {
             sinks,
             result,
             intermediate_nodes: Option::default(),
+            bounding_box: None,
         };
 
         Ok(x)
@@ -107,6 +144,32 @@ impl NetInternal {
     }
 }
 
+impl UnroutableSinkInternal {
+    fn from_external(external: &UnroutableSinkExternal, graph: &FabricGraph) -> MapExternalResult<Self> {
+        let map_id = |name: &Node| {
+            graph
+                .index
+                .get(&name.id())
+                .copied()
+                .ok_or_else(|| MapExternalError::Id(name.id()))
+        };
+        Ok(Self {
+            signal: map_id(&external.signal)?,
+            sink: map_id(&external.sink)?,
+            reason: external.reason.clone(),
+        })
+    }
+
+    #[must_use]
+    pub fn to_external(&self, graph: &FabricGraph) -> UnroutableSinkExternal {
+        UnroutableSinkExternal {
+            signal: graph.get_node(self.signal).clone(),
+            sink: graph.get_node(self.sink).clone(),
+            reason: self.reason.clone(),
+        }
+    }
+}
+
 impl NetResultInternal {
     fn from_external(graph: &FabricGraph, result: &NetResultExternal) -> MapExternalResult<Self> {
         let map_id = |name: &String| {
@@ -121,7 +184,7 @@ impl NetResultInternal {
             .nodes
             .iter()
             .map(|a| map_id(&a.id()))
-            .collect::<MapExternalResult<HashSet<NodeId>>>()
+            .collect::<MapExternalResult<BTreeSet<NodeId>>>()
             .map_err(|e| MapExternalError::NetResultNodes(Box::new(e)))?;
         let paths = result
             .paths
@@ -134,7 +197,7 @@ impl NetResultInternal {
                     .collect::<MapExternalResult<Vec<NodeId>>>()?;
                 Ok((sink, path))
             })
-            .collect::<MapExternalResult<HashMap<NodeId, Vec<NodeId>>>>()
+            .collect::<MapExternalResult<BTreeMap<NodeId, Vec<NodeId>>>>()
             .map_err(|e| MapExternalError::NetResultPaths(Box::new(e)))?;
         Ok(Self { paths, nodes })
     }
@@ -146,7 +209,7 @@ impl NetResultInternal {
             .iter()
             .map(|a| graph.get_node(*a))
             .cloned()
-            .collect::<HashSet<Node>>();
+            .collect::<BTreeSet<Node>>();
         let paths = self
             .paths
             .iter()
@@ -156,7 +219,7 @@ impl NetResultInternal {
                     path.iter().map(|c| graph.get_node(*c)).cloned().collect::<Vec<Node>>(),
                 )
             })
-            .collect::<HashMap<Node, Vec<Node>>>();
+            .collect::<BTreeMap<Node, Vec<Node>>>();
 
         NetResultExternal { paths, nodes }
     }
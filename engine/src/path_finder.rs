@@ -4,18 +4,36 @@
 //! conflict-driven optimization. This module contains functions to execute
 //! routing iterations, log results, and validate routing correctness.
 #![macro_use]
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Display, Formatter};
 use std::sync::atomic::AtomicU64;
 use std::time::{Duration, Instant};
 
 use crate::fabric::graph::Fabric;
-use crate::fabric::{graph::FabricGraph, node::NodeId};
-use crate::netlist::NetInternal;
+use crate::fabric::{
+    bounding_box::BoundingBox,
+    graph::FabricGraph,
+    node::{Node, NodeId},
+};
+use crate::convergence::{CancellationToken, Convergence, Interrupt, StagnationResponse, Stagnation, StopCriteria, StopReason};
+use crate::net_ordering::NetOrdering;
+use crate::netlist::{NetInternal, NetResultInternal, UnroutableSinkInternal};
 use crate::solver::RouteNet;
 use crate::{FabricError, FabricResult, Logging, netlist::NetListInternal};
-use crate::{LogInstance, SlackReport};
+use crate::{LogInstance, SlackReport, TileManager, create_fasm, netlist::NetListExternal};
+
+/// Selects the nets that are re-routed in every iteration
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+pub enum RipUp {
+    /// Every net is ripped up and re-routed
+    #[default]
+    All,
+    /// Only nets using a node that was overused in the previous iteration are re-routed,
+    /// all other nets keep their result
+    Congested,
+}
 
 /// Test case parameters for running a routing algorithm.
 #[derive(Deserialize, Debug, Clone, Serialize)]
@@ -26,6 +44,31 @@ pub struct Config {
     pub hist_factor: f32,
     /// The maximum iterations the path finder algorithm will try to solve the routing
     pub max_iterations: usize,
+    /// Tiles added around the pins of a net to restrict its searches, `None` searches the whole fabric
+    #[serde(default)]
+    pub bounding_box_margin: Option<u8>,
+    #[serde(default)]
+    pub rip_up: RipUp,
+    /// Routes batches of nets with disjoint bounding boxes in parallel
+    #[serde(default)]
+    pub parallel_nets: bool,
+    /// Order in which the nets are routed in every iteration
+    #[serde(default)]
+    pub net_ordering: NetOrdering,
+    /// Criteria to give up before `max_iterations`
+    #[serde(default)]
+    pub stop_criteria: StopCriteria,
+    #[serde(default)]
+    pub stagnation: Stagnation,
+    /// Stops the routing from another thread
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+    /// Excludes sinks no path is found to instead of failing, see [`NetListInternal::unroutable`]
+    #[serde(default)]
+    pub skip_unroutable: bool,
+    /// Keeps a copy of the routing with the fewest conflicts for the error of a failed run
+    #[serde(default)]
+    pub keep_best_routing: bool,
 }
 
 static COUNTER: AtomicU64 = AtomicU64::new(0);
@@ -36,8 +79,23 @@ impl Config {
             id,
             hist_factor,
             max_iterations,
+            bounding_box_margin: None,
+            rip_up: RipUp::All,
+            parallel_nets: false,
+            net_ordering: NetOrdering::AsGiven,
+            stop_criteria: StopCriteria::default(),
+            stagnation: Stagnation::default(),
+            cancellation: None,
+            skip_unroutable: false,
+            keep_best_routing: false,
         }
     }
+
+    /// Returns `true` if the path finder copies every improved routing. Interrupted runs have no
+    /// other consistent routing to report.
+    const fn snapshots_best(&self) -> bool {
+        self.keep_best_routing || self.cancellation.is_some() || self.stop_criteria.time_limit.is_some()
+    }
 }
 impl Default for Config {
     fn default() -> Self {
@@ -74,26 +132,33 @@ where
     R: RouteNet,
     L: Logging,
 {
-    let hist_fac = config.hist_factor;
     let mut iteration_report = Vec::new();
+    let mut overused = None;
+    let mut convergence = Convergence::new();
+    let interrupt = Interrupt::new(config.cancellation.clone(), config.stop_criteria.time_limit);
+    let mut best = None;
+    let mut reorder = None;
 
     let mut i = 0;
-    let mut last_conflicts = 0;
-    let mut same_conflicts = 0;
+    assign_bounding_boxes(&fabric.graph, &mut net_list.plan, config.bounding_box_margin);
     solver.pre_process(fabric, &mut net_list.plan)?;
     let max_iterations = config.max_iterations;
 
     loop {
         let time1 = Instant::now();
-        let conflicts = iteration(fabric, &mut net_list.plan, solver, hist_fac)
-            .map_err(|e| FabricError::IterationError { source: e.into() })?;
+        fabric.graph.present_factor = fabric.graph.cost_model.present_factor(i);
+        reorder.take().unwrap_or(config.net_ordering).sort(fabric, &mut net_list.plan, i, overused.as_ref());
+        let Some(conflicts) = iteration(fabric, &mut net_list.plan, solver, config, &mut overused, &interrupt, &mut net_list.unroutable)
+            .map_err(|e| FabricError::IterationError { source: e.into() })?
+        else {
+            return Err(routing_interrupted(fabric, best, iteration_report, &interrupt));
+        };
         let duration = time1.elapsed();
         let result = analyze_result(i, conflicts, duration, &fabric.graph, net_list, config);
-
-        if result.conflicts == last_conflicts {
-            same_conflicts += 1;
+        convergence.record(result.conflicts);
+        if config.snapshots_best() {
+            keep_best(&mut best, net_list, result.conflicts);
         }
-        last_conflicts = result.conflicts;
 
         if result.conflicts == 0 {
             logger.log(&LogInstance::RouterIteration(&result))?;
@@ -103,16 +168,18 @@ where
 
         if i == max_iterations {
             logger.log(&LogInstance::RouterIteration(&result))?;
-            let congestion_report = congestion_report(net_list);
-            let congestion_report = CongestionReportExtern::from_intern(&congestion_report, &fabric.graph);
-            return Err(FabricError::RoutingMaxIterationsReached {
-                congestion_report,
-                iteration_report,
-            });
+            iteration_report.push(result);
+            return Err(routing_failed(fabric, net_list, best.as_ref().map(|(_, best)| best), iteration_report, None));
         }
 
-        if same_conflicts == 200 {
-            solver.pre_process(fabric, &mut net_list.plan)?;
+        if let Some(reason) = convergence.stop_reason(&config.stop_criteria, max_iterations) {
+            logger.log(&LogInstance::RouterIteration(&result))?;
+            iteration_report.push(result);
+            return Err(routing_failed(fabric, net_list, best.as_ref().map(|(_, best)| best), iteration_report, Some(reason)));
+        }
+
+        if convergence.stagnated(&config.stagnation) {
+            reorder = respond_to_stagnation(fabric, net_list, solver, config, overused.as_ref())?;
         }
         logger.log(&LogInstance::RouterIteration(&result))?;
         iteration_report.push(result);
@@ -133,28 +200,35 @@ where
     L: Logging,
     T: TimingAnalysis,
 {
-    let hist_fac = config.hist_factor;
     let mut iteration_report = Vec::new();
+    let mut overused = None;
+    let mut convergence = Convergence::new();
+    let interrupt = Interrupt::new(config.cancellation.clone(), config.stop_criteria.time_limit);
+    let mut best = None;
+    let mut reorder = None;
 
     let mut i = 0;
-    let mut last_conflicts = 0;
-    let mut same_conflicts = 0;
+    assign_bounding_boxes(&fabric.graph, &mut net_list.plan, config.bounding_box_margin);
     solver.pre_process(fabric, &mut net_list.plan)?;
     let max_iterations = config.max_iterations;
 
     loop {
         let time1 = Instant::now();
-        let conflicts = iteration(fabric, &mut net_list.plan, solver, hist_fac)
-            .map_err(|e| FabricError::IterationError { source: e.into() })?;
+        fabric.graph.present_factor = fabric.graph.cost_model.present_factor(i);
+        reorder.take().unwrap_or(config.net_ordering).sort(fabric, &mut net_list.plan, i, overused.as_ref());
+        let Some(conflicts) = iteration(fabric, &mut net_list.plan, solver, config, &mut overused, &interrupt, &mut net_list.unroutable)
+            .map_err(|e| FabricError::IterationError { source: e.into() })?
+        else {
+            return Err(routing_interrupted(fabric, best, iteration_report, &interrupt));
+        };
         let duration = time1.elapsed();
 
         iteration_report.push(analyze_result(i, conflicts, duration, &fabric.graph, net_list, config));
         let result = iteration_report.last().unwrap();
-
-        if result.conflicts == last_conflicts {
-            same_conflicts += 1;
+        convergence.record(result.conflicts);
+        if config.snapshots_best() {
+            keep_best(&mut best, net_list, result.conflicts);
         }
-        last_conflicts = result.conflicts;
 
         if result.conflicts == 0 {
             let slack_report = sta.timing_analysis(fabric, net_list)?;
@@ -166,6 +240,9 @@ where
                 return Ok(iteration_report);
             }
             fabric.slack_report = Some(slack_report);
+            // New criticalities affect every net, not only congested ones
+            overused = None;
+            convergence.restart();
         } else {
             logger.log(&LogInstance::RouterStaIteration(&StaIterationResult {
                 iteration_result: result,
@@ -174,21 +251,103 @@ where
         }
 
         if i == max_iterations {
-            let congestion_report = congestion_report(net_list);
-            let congestion_report = CongestionReportExtern::from_intern(&congestion_report, &fabric.graph);
-            return Err(FabricError::RoutingMaxIterationsReached {
-                congestion_report,
-                iteration_report,
-            });
+            return Err(routing_failed(fabric, net_list, best.as_ref().map(|(_, best)| best), iteration_report, None));
+        }
+
+        if let Some(reason) = convergence.stop_reason(&config.stop_criteria, max_iterations) {
+            return Err(routing_failed(fabric, net_list, best.as_ref().map(|(_, best)| best), iteration_report, Some(reason)));
         }
 
-        if same_conflicts == 200 {
-            solver.pre_process(fabric, &mut net_list.plan)?;
+        if convergence.stagnated(&config.stagnation) {
+            reorder = respond_to_stagnation(fabric, net_list, solver, config, overused.as_ref())?;
         }
         i += 1;
     }
 }
 
+/// Remembers the routing of `net_list` if it has fewer `conflicts` than the best one so far
+fn keep_best(best: &mut Option<(usize, NetListInternal)>, net_list: &NetListInternal, conflicts: usize) {
+    if best.as_ref().is_none_or(|(best_conflicts, _)| conflicts < *best_conflicts) {
+        *best = Some((conflicts, net_list.clone()));
+    }
+}
+
+/// Error for a routing stopped by `interrupt`, carries the `best` routing of the finished iterations
+fn routing_interrupted(
+    fabric: &Fabric,
+    best: Option<(usize, NetListInternal)>,
+    iteration_report: Vec<IterationResult>,
+    interrupt: &Interrupt,
+) -> FabricError {
+    FabricError::RoutingInterrupted {
+        reason: interrupt.reason().unwrap_or(StopReason::Cancelled),
+        best_routing: best.map(|(_, net_list)| Box::new(PartialRouting::new(&net_list, &fabric.graph))),
+        iteration_report,
+    }
+}
+
+/// Error for a routing that did not converge, `reason` is `None` if `max_iterations` was reached.
+/// Carries the `best` routing of the finished iterations if one was kept, the last one otherwise.
+fn routing_failed(
+    fabric: &Fabric,
+    net_list: &NetListInternal,
+    best: Option<&NetListInternal>,
+    iteration_report: Vec<IterationResult>,
+    reason: Option<StopReason>,
+) -> FabricError {
+    let congestion_report = congestion_report(net_list);
+    let congestion_report = Box::new(CongestionReportExtern::from_intern(&congestion_report, &fabric.graph));
+    let best_routing = Some(Box::new(PartialRouting::new(best.unwrap_or(net_list), &fabric.graph)));
+    match reason {
+        Some(reason) => FabricError::RoutingStopped {
+            reason,
+            congestion_report,
+            iteration_report,
+            best_routing,
+        },
+        None => FabricError::RoutingMaxIterationsReached {
+            congestion_report,
+            iteration_report,
+            best_routing,
+        },
+    }
+}
+
+/// Applies the [`StagnationResponse`]s of `config` after an iteration.
+/// `overused` are the nodes overused in that iteration.
+///
+/// Returns the ordering that replaces [`Config::net_ordering`] for the next iteration only, the
+/// net ordering would otherwise undo a reorder before it took effect.
+fn respond_to_stagnation<R: RouteNet>(
+    fabric: &mut Fabric,
+    net_list: &mut NetListInternal,
+    solver: &R,
+    config: &Config,
+    overused: Option<&BTreeSet<NodeId>>,
+) -> FabricResult<Option<NetOrdering>> {
+    let mut reorder = None;
+    for response in &config.stagnation.responses {
+        match *response {
+            StagnationResponse::PreProcess => solver.pre_process(fabric, &mut net_list.plan)?,
+            StagnationResponse::HistoricBoost(factor) => {
+                for node in overused.into_iter().flatten() {
+                    fabric.graph.get_costs_mut(*node).historic_cost *= factor;
+                }
+            }
+            StagnationResponse::RandomReorder { seed } => reorder = Some(NetOrdering::Random { seed }),
+            StagnationResponse::WidenBoundingBoxes(margin) => {
+                let Some(extent) = fabric.graph.extent() else {
+                    continue;
+                };
+                for net in &mut net_list.plan {
+                    net.bounding_box = net.bounding_box.map(|bounding_box| bounding_box.expand(margin, &extent));
+                }
+            }
+        }
+    }
+    Ok(reorder)
+}
+
 fn congestion_report(net_list: &NetListInternal) -> CongestionReportIntern {
     let mut congestion: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
     let mut net_congestion: HashMap<NodeId, f32> = HashMap::new();
@@ -226,6 +385,48 @@ fn congestion_report(net_list: &NetListInternal) -> CongestionReportIntern {
     }
 }
 
+/// Best-effort routing of a failed run, the routing of the iteration with the fewest conflicts, or of
+/// the last iteration if the run kept no copy, see [`crate::RoutingConfigBuilder::keep_best_routing`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PartialRouting {
+    pub net_list: NetListExternal,
+    /// Nodes used by more than one net and the signals of these nets
+    pub conflicts: BTreeMap<String, Vec<String>>,
+}
+
+impl PartialRouting {
+    #[must_use]
+    pub fn new(net_list: &NetListInternal, graph: &FabricGraph) -> Self {
+        let conflicts = congestion_report(net_list)
+            .congestion
+            .into_iter()
+            .map(|(node, signals)| (node.name(graph), signals.into_iter().map(|signal| signal.name(graph)).collect()))
+            .collect();
+        Self {
+            net_list: net_list.to_external(graph),
+            conflicts,
+        }
+    }
+
+    /// FASM of the routing preceded by a comment for every conflict
+    ///
+    /// The LUT `INIT`s are taken from `tile_manager` as they are, the partial routing does not
+    /// permute them itself. Pass the tile manager of the failed run: [`crate::route`] sets its pin
+    /// permutations and route-through buffers from this routing before it returns the error.
+    ///
+    /// # Errors
+    /// Fails if a net of the routing has no result
+    pub fn to_fasm(&self, tile_manager: &TileManager) -> FabricResult<String> {
+        let conflicts = self
+            .conflicts
+            .iter()
+            .map(|(node, signals)| format!("# Conflict: {node} is used by {}", signals.join(", ")))
+            .collect::<Vec<String>>()
+            .join("\n");
+        Ok(format!("{conflicts}\n{}", create_fasm(&self.net_list, tile_manager)?))
+    }
+}
+
 #[derive(Debug)]
 pub struct CongestionReportIntern {
     pub congestion: HashMap<NodeId, Vec<NodeId>>,
@@ -265,26 +466,220 @@ pub struct CongestionReportExtern {
     pub congestion: HashMap<String, Vec<String>>,
     pub net_congestion: HashMap<String, f32>,
 }
-/// Perform a single iteration of routing for all routing requests.
+/// Restricts every net to the box around its pins grown by `margin` tiles
+fn assign_bounding_boxes(graph: &FabricGraph, routing: &mut [NetInternal], margin: Option<u8>) {
+    let Some(extent) = graph.extent() else {
+        return;
+    };
+    for net in routing {
+        net.bounding_box = margin.and_then(|margin| net.pin_bounding_box(graph).map(|bb| bb.expand(margin, &extent)));
+    }
+}
+
+/// Grows the bounding box of `net` by one tile. Returns `false` if the net already searches the whole fabric.
+fn widen_bounding_box(net: &mut NetInternal, extent: Option<BoundingBox>) -> bool {
+    match (net.bounding_box, extent) {
+        (Some(bounding_box), Some(extent)) if !bounding_box.covers(&extent) => {
+            net.bounding_box = Some(bounding_box.expand(1, &extent));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Routes `net` on its own. While the net fails to route its bounding box is widened and the net is
+/// retried. Failed searches of a net that already searches the whole fabric are collected in
+/// `routing_failed`, other errors are returned.
 ///
-/// Updates node usages and calculates conflicts
-pub fn iteration(fabric: &mut Fabric, routing: &mut [NetInternal], solver: &dyn RouteNet, hist_fac: f32) -> FabricResult<usize> {
-    let mut routing_failed = vec![];
-    for net in &mut *routing {
-        if let Err(e) = solver.solve(fabric, net) {
-            if let FabricError::PathfindingFailed { start, sink } = e {
+/// With [`Config::skip_unroutable`] the sinks of a failed search over the whole fabric are moved to
+/// `unroutable` instead and the remaining sinks are retried. A sink is only excluded once widening
+/// is exhausted, a failure inside a bounding box does not prove it unroutable.
+fn route_net(
+    fabric: &mut Fabric,
+    net: &mut NetInternal,
+    solver: &dyn RouteNet,
+    config: &Config,
+    routing_failed: &mut Vec<(Node, Node)>,
+    unroutable: &mut Vec<UnroutableSinkInternal>,
+) -> FabricResult<()> {
+    let extent = fabric.graph.extent();
+    loop {
+        if net.sinks.is_empty() {
+            net.result = Some(NetResultInternal {
+                paths: BTreeMap::new(),
+                nodes: BTreeSet::new(),
+            });
+            return Ok(());
+        }
+        let Err(err) = solver.solve(fabric, net) else {
+            return Ok(());
+        };
+        if widen_bounding_box(net, extent) {
+            continue;
+        }
+        match err {
+            err if config.skip_unroutable && let Some(sinks) = failed_sinks(&fabric.graph, net, &err) => {
+                let reason = err.to_string();
+                for sink in sinks {
+                    exclude_sink(net, sink, reason.clone(), unroutable);
+                }
+            }
+            FabricError::PathfindingFailed { start, sink } => {
                 routing_failed.push((start, sink));
+                return Ok(());
+            }
+            err => return Err(err),
+        }
+    }
+}
+
+/// Sinks of `net` a failed search reported by `err` was looking for, `None` if `err` is no search failure
+fn failed_sinks(graph: &FabricGraph, net: &NetInternal, err: &FabricError) -> Option<Vec<NodeId>> {
+    let sinks = net
+        .sinks
+        .iter()
+        .copied()
+        .filter(|id| match err {
+            FabricError::PathfindingFailed { sink, .. } => graph.get_node(*id) == sink,
+            FabricError::FindOnePathfindingFailed { sink, .. } => sink.contains(graph.get_node(*id)),
+            _ => false,
+        })
+        .collect::<Vec<NodeId>>();
+    (!sinks.is_empty()).then_some(sinks)
+}
+
+/// Removes `sink` from `net` and records it in `unroutable`
+fn exclude_sink(net: &mut NetInternal, sink: NodeId, reason: String, unroutable: &mut Vec<UnroutableSinkInternal>) {
+    net.sinks.retain(|other| *other != sink);
+    if let Some(steiner_tree) = &mut net.intermediate_nodes {
+        steiner_tree.remove(&sink);
+    }
+    unroutable.push(UnroutableSinkInternal {
+        signal: net.signal,
+        sink,
+        reason,
+    });
+}
+
+/// Adds the nodes used by `net` to the usage of the graph
+fn add_usage(graph: &mut FabricGraph, net: &NetInternal) {
+    if let Some(result) = &net.result {
+        result.nodes.iter().for_each(|index| {
+            graph.get_costs_mut(*index).usage += 1;
+        });
+    }
+}
+
+/// Groups `nets` into batches whose bounding boxes do not overlap, each net joins the first batch
+/// it fits into. Nets without a bounding box use the box of their pins. Returns the batch of every net
+/// and the box it was placed with.
+fn partition_batches(graph: &FabricGraph, nets: &[&mut NetInternal]) -> Vec<(usize, BoundingBox)> {
+    let Some(extent) = graph.extent() else {
+        return Vec::new();
+    };
+    let mut batches: Vec<Vec<BoundingBox>> = Vec::new();
+    nets.iter()
+        .map(|net| {
+            let bounding_box = net.bounding_box.or_else(|| net.pin_bounding_box(graph)).unwrap_or(extent);
+            if let Some(index) = batches
+                .iter()
+                .position(|boxes| boxes.iter().all(|other| !other.intersects(&bounding_box)))
+            {
+                batches[index].push(bounding_box);
+                (index, bounding_box)
             } else {
-                return Err(e);
+                batches.push(vec![bounding_box]);
+                (batches.len() - 1, bounding_box)
+            }
+        })
+        .collect()
+}
+
+/// Perform a single iteration of routing for all routing requests.
+///
+/// Updates node usages and calculates conflicts. Nets using an overused node get a wider bounding box.
+/// With [`RipUp::Congested`] only nets using a node of `overused` are re-routed, the usage of all
+/// other nets is kept. `overused` is replaced by the nodes overused in this iteration, `None`
+/// re-routes every net.
+///
+/// With [`Config::parallel_nets`] the re-routed nets are grouped by [`partition_batches`]. The nets of a
+/// batch are routed in parallel with [`RouteNet::solve_shared`] against the usage of the previous
+/// batches and are merged back in net-list order, so the result does not depend on the thread scheduling.
+/// The searches of a batch stay inside the boxes the batch was formed with, so its nets never share a node.
+/// Nets that fail inside their box are routed serially with [`route_net`].
+///
+/// With [`Config::skip_unroutable`] sinks that cannot be reached are excluded from their nets and added
+/// to `unroutable`.
+///
+/// Returns `None` if `interrupt` stopped the iteration before all nets were routed.
+pub fn iteration(
+    fabric: &mut Fabric,
+    routing: &mut [NetInternal],
+    solver: &dyn RouteNet,
+    config: &Config,
+    overused: &mut Option<BTreeSet<NodeId>>,
+    interrupt: &Interrupt,
+    unroutable: &mut Vec<UnroutableSinkInternal>,
+) -> FabricResult<Option<usize>> {
+    let keep = routing
+        .iter()
+        .map(|net| match (config.rip_up, &*overused, &net.result) {
+            (RipUp::Congested, Some(overused), Some(result)) => result.nodes.is_disjoint(overused),
+            _ => false,
+        })
+        .collect::<Vec<bool>>();
+    for (net, _) in routing.iter().zip(&keep).filter(|(_, keep)| **keep) {
+        add_usage(&mut fabric.graph, net);
+    }
+
+    let mut routing_failed = vec![];
+    let ripped = routing
+        .iter_mut()
+        .zip(&keep)
+        .filter(|(_, keep)| !**keep)
+        .map(|(net, _)| net)
+        .collect::<Vec<&mut NetInternal>>();
+    if config.parallel_nets {
+        let batches = partition_batches(&fabric.graph, &ripped);
+        let mut ripped = ripped.into_iter().zip(batches).collect::<Vec<(&mut NetInternal, (usize, BoundingBox))>>();
+        ripped.sort_by_key(|(_, (batch, _))| *batch);
+        for batch in ripped.chunk_by_mut(|a, b| a.1.0 == b.1.0) {
+            if interrupt.reason().is_some() {
+                fabric.graph.reset_usage();
+                return Ok(None);
+            }
+            let shared = {
+                let fabric = &*fabric;
+                batch
+                    .par_iter_mut()
+                    .map(|(net, (_, bounding_box))| {
+                        let own = net.bounding_box.replace(*bounding_box);
+                        let result = solver.solve_shared(fabric, net);
+                        net.bounding_box = own;
+                        result
+                    })
+                    .collect::<Vec<Option<FabricResult<()>>>>()
+            };
+            for ((net, _), result) in batch.iter_mut().zip(shared) {
+                match result {
+                    Some(Ok(())) => solver.commit(fabric, net)?,
+                    _ => route_net(fabric, net, solver, config, &mut routing_failed, unroutable)?,
+                }
+                add_usage(&mut fabric.graph, net);
             }
         }
-        if let Some(result) = &net.result {
-            result.nodes.iter().for_each(|index| {
-                fabric.graph.get_costs_mut(*index).usage += 1;
-            });
+    } else {
+        for net in ripped {
+            if interrupt.reason().is_some() {
+                fabric.graph.reset_usage();
+                return Ok(None);
+            }
+            route_net(fabric, net, solver, config, &mut routing_failed, unroutable)?;
+            add_usage(&mut fabric.graph, net);
         }
     }
     if !routing_failed.is_empty() {
+        fabric.graph.reset_usage();
         return Err(FabricError::Other(
             routing_failed
                 .iter()
@@ -293,13 +688,29 @@ pub fn iteration(fabric: &mut Fabric, routing: &mut [NetInternal], solver: &dyn
                 .join("\n"),
         ));
     }
-    let mut conflicts = 0;
-    for node in &mut fabric.graph.costs {
-        if node.update(hist_fac) {
-            conflicts += 1;
+
+    let now_overused = fabric
+        .graph
+        .costs
+        .iter()
+        .enumerate()
+        .filter(|(_, costs)| f32::from(costs.usage) > costs.capacity)
+        .map(|(index, _)| NodeId::new(index))
+        .collect::<BTreeSet<NodeId>>();
+    let extent = fabric.graph.extent();
+    for net in &mut *routing {
+        if net.result.as_ref().is_some_and(|result| !result.nodes.is_disjoint(&now_overused)) {
+            widen_bounding_box(net, extent);
         }
     }
-    Ok(conflicts)
+    let cost_model = fabric.graph.cost_model.clone();
+    for node in &mut fabric.graph.costs {
+        cost_model.update_historic(node, config.hist_factor);
+        node.usage = 0;
+    }
+    let conflicts = now_overused.len();
+    *overused = Some(now_overused);
+    Ok(Some(conflicts))
 }
 
 fn analyze_result(
@@ -412,3 +823,183 @@ impl Display for IterationResult {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DEFAULT_CONSTANT_SEARCH_RADIUS, SimpleSolver, SteinerSolver, TileManager,
+        generator::{Fanout, TestConfig, generate_test},
+    };
+    use testing_utils::get_test_data_path;
+
+    fn load_fabric() -> Fabric {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        Fabric::new(graph, tile_manager)
+    }
+
+    #[test]
+    fn test_parallel_iteration_is_deterministic() {
+        let mut fabric = load_fabric();
+        let test_config = TestConfig {
+            seed: 3,
+            percentage: 0.4,
+            fanout: Fanout::Fixed(2),
+            ..TestConfig::default()
+        };
+        let external = generate_test(&fabric.graph, &test_config).unwrap();
+        let mut net_list = NetListInternal::from_external(&fabric.graph, &external).unwrap();
+        fabric.check_pathing(&mut net_list, DEFAULT_CONSTANT_SEARCH_RADIUS, false).unwrap();
+        let config = Config {
+            bounding_box_margin: Some(1),
+            parallel_nets: true,
+            ..Config::default()
+        };
+        assign_bounding_boxes(&fabric.graph, &mut net_list.plan, config.bounding_box_margin);
+
+        let mut other_fabric = load_fabric();
+        for net in &net_list.plan {
+            other_fabric.check_and_mark_node(net.signal);
+            net.sinks.iter().for_each(|sink| other_fabric.check_and_mark_node(*sink));
+        }
+        let mut other_plan = net_list.plan.clone();
+
+        let batches = partition_batches(&fabric.graph, &net_list.plan.iter_mut().collect::<Vec<&mut NetInternal>>());
+        assert!(batches.iter().any(|(batch, _)| *batch > 0));
+
+        let interrupt = Interrupt::default();
+        let conflicts = iteration(&mut fabric, &mut net_list.plan, &SimpleSolver, &config, &mut None, &interrupt, &mut Vec::new()).unwrap();
+        let other_conflicts = iteration(&mut other_fabric, &mut other_plan, &SimpleSolver, &config, &mut None, &interrupt, &mut Vec::new()).unwrap();
+        assert_eq!(conflicts, other_conflicts);
+        for (net, other) in net_list.plan.iter().zip(&other_plan) {
+            assert_eq!(net.result.as_ref().unwrap().nodes, other.result.as_ref().unwrap().nodes);
+        }
+    }
+
+    #[test]
+    fn test_parallel_batches_stay_in_their_boxes() {
+        let mut fabric = load_fabric();
+        let test_config = TestConfig {
+            seed: 3,
+            percentage: 0.8,
+            fanout: Fanout::Fixed(4),
+            ..TestConfig::default()
+        };
+        let external = generate_test(&fabric.graph, &test_config).unwrap();
+        let mut net_list = NetListInternal::from_external(&fabric.graph, &external).unwrap();
+        fabric.check_pathing(&mut net_list, DEFAULT_CONSTANT_SEARCH_RADIUS, false).unwrap();
+        // Without a margin the nets have no bounding box, the batches use the boxes of their pins
+        let config = Config {
+            parallel_nets: true,
+            ..Config::default()
+        };
+        let batches = partition_batches(&fabric.graph, &net_list.plan.iter_mut().collect::<Vec<&mut NetInternal>>());
+        // Nets that can be routed inside their batch box, the rest falls back to a serial search
+        let fits = net_list
+            .plan
+            .iter()
+            .zip(&batches)
+            .map(|(net, (_, bounding_box))| {
+                let mut net = net.clone();
+                net.bounding_box = Some(*bounding_box);
+                matches!(SimpleSolver.solve_shared(&fabric, &mut net), Some(Ok(())))
+            })
+            .collect::<Vec<bool>>();
+        assert!(fits.iter().filter(|fits| **fits).count() > 1);
+
+        let interrupt = Interrupt::default();
+        iteration(&mut fabric, &mut net_list.plan, &SimpleSolver, &config, &mut None, &interrupt, &mut Vec::new()).unwrap();
+        for ((net, (_, bounding_box)), _) in net_list.plan.iter().zip(&batches).zip(&fits).filter(|(_, fits)| **fits) {
+            assert!(net.bounding_box.is_none());
+            let nodes = &net.result.as_ref().unwrap().nodes;
+            assert!(nodes.iter().all(|node| bounding_box.contains(fabric.graph.get_node(*node).tile)));
+        }
+    }
+
+    #[test]
+    fn test_random_reorder_outlasts_the_net_ordering() {
+        let mut fabric = load_fabric();
+        let test_config = TestConfig {
+            seed: 3,
+            percentage: 0.4,
+            fanout: Fanout::mixed(4),
+            ..TestConfig::default()
+        };
+        let external = generate_test(&fabric.graph, &test_config).unwrap();
+        let mut net_list = NetListInternal::from_external(&fabric.graph, &external).unwrap();
+        fabric.check_pathing(&mut net_list, DEFAULT_CONSTANT_SEARCH_RADIUS, false).unwrap();
+        let config = Config {
+            net_ordering: NetOrdering::Fanout,
+            stagnation: Stagnation {
+                window: 1,
+                responses: vec![StagnationResponse::RandomReorder { seed: 1 }],
+            },
+            ..Config::default()
+        };
+        let signals = |net_list: &NetListInternal| net_list.plan.iter().map(|net| net.signal).collect::<Vec<NodeId>>();
+        config.net_ordering.sort(&fabric, &mut net_list.plan, 0, None);
+        let by_fanout = signals(&net_list);
+
+        let reorder = respond_to_stagnation(&mut fabric, &mut net_list, &SimpleSolver, &config, None).unwrap();
+        assert_eq!(reorder, Some(NetOrdering::Random { seed: 1 }));
+        // The next iteration is routed in the random order, the one after that by fanout again
+        reorder.unwrap_or(config.net_ordering).sort(&fabric, &mut net_list.plan, 1, None);
+        assert_ne!(signals(&net_list), by_fanout);
+        config.net_ordering.sort(&fabric, &mut net_list.plan, 2, None);
+        assert_eq!(signals(&net_list), by_fanout);
+    }
+
+    #[test]
+    fn test_iteration_skips_unroutable_sinks() {
+        // All solvers report a failed search with its sink
+        assert_skips_unroutable_sinks(&SimpleSolver);
+        assert_skips_unroutable_sinks(&SteinerSolver);
+    }
+
+    fn assert_skips_unroutable_sinks(solver: &dyn RouteNet) {
+        let mut fabric = load_fabric();
+        let id = |fabric: &Fabric, name: &str| *fabric.graph.get_node_id(name).unwrap();
+        let (signal, routable, unreachable) = (
+            id(&fabric, "X1Y1.J2MID_ABa_END0"),
+            id(&fabric, "X1Y1.LA_I0"),
+            id(&fabric, "X3Y3.LC_I1"),
+        );
+        // The sink of the second net lies outside its box, it is routable once the box is widened
+        let (remote_signal, remote) = (id(&fabric, "X1Y1.LD_O"), id(&fabric, "X2Y2.LC_I0"));
+        let mut plan = vec![
+            NetInternal {
+                signal,
+                sinks: vec![routable, unreachable],
+                result: None,
+                intermediate_nodes: None,
+                bounding_box: None,
+            },
+            NetInternal {
+                signal: remote_signal,
+                sinks: vec![remote],
+                result: None,
+                intermediate_nodes: None,
+                bounding_box: Some(BoundingBox::new(fabric.graph.get_node(remote_signal).tile)),
+            },
+        ];
+        let interrupt = Interrupt::default();
+        let mut unroutable = Vec::new();
+        let config = Config::default();
+        assert!(iteration(&mut fabric, &mut plan.clone(), solver, &config, &mut None, &interrupt, &mut unroutable).is_err());
+
+        // The failed iteration claimed LUT inputs, the second one starts from a fresh fabric
+        let mut fabric = load_fabric();
+        let config = Config {
+            skip_unroutable: true,
+            ..Config::default()
+        };
+        let conflicts = iteration(&mut fabric, &mut plan, solver, &config, &mut None, &interrupt, &mut unroutable).unwrap();
+        assert_eq!(conflicts, Some(0));
+        assert_eq!(unroutable.len(), 1);
+        assert_eq!((unroutable[0].signal, unroutable[0].sink), (signal, unreachable));
+        assert_eq!(plan[0].sinks, vec![routable]);
+        assert_eq!(plan[0].result.as_ref().unwrap().paths.len(), 1);
+        assert_eq!(plan[1].result.as_ref().unwrap().paths.len(), 1);
+    }
+}
@@ -0,0 +1,248 @@
+//! Module `routability`
+//!
+//! Statistics of an unrouted net-list compared to the resources of the fabric.
+//! The report is meant to detect designs that are likely unroutable before the
+//! path finder spends its iterations on them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+};
+
+use serde::Serialize;
+
+use crate::{
+    Fabric, NetListExternal,
+    fabric::{
+        bounding_box::BoundingBox,
+        node::{Node, NodeType, TileId},
+    },
+};
+
+/// Statistics of a single net
+#[derive(Debug, Clone, Serialize)]
+pub struct NetStatistics {
+    pub signal: Node,
+    pub fanout: usize,
+    pub bounding_box: BoundingBox,
+    /// Half-perimeter wire length of the bounding box in tiles
+    pub half_perimeter: usize,
+}
+
+/// Demand and supply of LUT input pins of a tile
+#[derive(Debug, Clone, Serialize)]
+pub struct TilePinUsage {
+    pub tile: TileId,
+    /// Connections ending on a LUT input of the tile
+    pub demand: usize,
+    /// LUT input pins of the tile
+    pub supply: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CutDirection {
+    /// Cut between column `position` and `position + 1`
+    Vertical,
+    /// Cut between row `position` and `position + 1`
+    Horizontal,
+}
+
+/// Estimate of the routing demand and supply across a cut-line of the fabric
+#[derive(Debug, Clone, Serialize)]
+pub struct CutLine {
+    pub direction: CutDirection,
+    pub position: u8,
+    /// Nets whose bounding box spans both sides of the cut
+    pub demand: usize,
+    /// Wires of the fabric crossing the cut
+    pub supply: usize,
+}
+
+impl CutLine {
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn utilization(&self) -> f32 {
+        if self.supply == 0 {
+            return if self.demand == 0 { 0.0 } else { f32::INFINITY };
+        }
+        self.demand as f32 / self.supply as f32
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum RoutabilityWarning {
+    /// More connections end on LUT inputs of a tile than the tile has LUT inputs
+    TilePinDemandExceedsSupply(TilePinUsage),
+    /// A sink is listed by several nets
+    SinkDrivenByMultipleNets { sink: Node, drivers: usize },
+    /// More nets cross a cut-line than wires cross it
+    CutLineOverloaded(CutLine),
+}
+
+impl Display for RoutabilityWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TilePinDemandExceedsSupply(usage) => write!(
+                f,
+                "Tile {} needs {} LUT inputs but only has {}.",
+                usage.tile, usage.demand, usage.supply
+            ),
+            Self::SinkDrivenByMultipleNets { sink, drivers } => {
+                write!(f, "Sink {sink} is driven by {drivers} nets.")
+            }
+            Self::CutLineOverloaded(cut) => write!(
+                f,
+                "{:?} cut-line {} is crossed by {} nets but only {} wires.",
+                cut.direction, cut.position, cut.demand, cut.supply
+            ),
+        }
+    }
+}
+
+/// Report about a net-list created by [`analyze_routability`]
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutabilityReport {
+    pub net_count: usize,
+    pub connection_count: usize,
+    /// Maps a fanout to the number of nets having that fanout
+    pub fanout_histogram: BTreeMap<usize, usize>,
+    pub nets: Vec<NetStatistics>,
+    /// Sum of the half-perimeter wire length of all nets
+    pub total_half_perimeter: usize,
+    /// Nets driven by `VCC0` or `GND0`
+    pub constant_nets: usize,
+    pub tile_pin_usage: Vec<TilePinUsage>,
+    pub cut_lines: Vec<CutLine>,
+    pub warnings: Vec<RoutabilityWarning>,
+}
+
+impl RoutabilityReport {
+    #[must_use]
+    pub const fn is_likely_unroutable(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+}
+
+/// Analyzes an unrouted `net_list` against the resources of `fabric`
+#[must_use]
+pub fn analyze_routability(fabric: &Fabric, net_list: &NetListExternal) -> RoutabilityReport {
+    let mut fanout_histogram = BTreeMap::new();
+    let mut nets = Vec::with_capacity(net_list.plan.len());
+    let mut pin_demand: HashMap<TileId, usize> = HashMap::new();
+    let mut sink_drivers: BTreeMap<&Node, usize> = BTreeMap::new();
+    let mut constant_nets = 0;
+
+    for net in &net_list.plan {
+        *fanout_histogram.entry(net.sinks.len()).or_insert(0) += 1;
+        if net.signal.constant().is_some() {
+            constant_nets += 1;
+        }
+        for sink in &net.sinks {
+            *sink_drivers.entry(sink).or_insert(0) += 1;
+            if matches!(sink.typ, NodeType::LutInput(_)) {
+                *pin_demand.entry(sink.tile).or_insert(0) += 1;
+            }
+        }
+        let bounding_box = BoundingBox::from_tiles(net.sinks.iter().map(|sink| sink.tile).chain([net.signal.tile]))
+            .unwrap_or_else(|| BoundingBox::new(net.signal.tile));
+        nets.push(NetStatistics {
+            signal: net.signal.clone(),
+            fanout: net.sinks.len(),
+            bounding_box,
+            half_perimeter: bounding_box.half_perimeter(),
+        });
+    }
+
+    let supply = fabric.tile_manager.lut_input_supply().collect::<HashMap<TileId, usize>>();
+    let mut tile_pin_usage = pin_demand
+        .into_iter()
+        .map(|(tile, demand)| TilePinUsage {
+            tile,
+            demand,
+            supply: supply.get(&tile).copied().unwrap_or(0),
+        })
+        .collect::<Vec<TilePinUsage>>();
+    tile_pin_usage.sort_by_key(|usage| (usage.tile.0, usage.tile.1));
+
+    let cut_lines = cut_lines(fabric, &nets);
+
+    let mut warnings = Vec::new();
+    warnings.extend(
+        tile_pin_usage
+            .iter()
+            .filter(|usage| usage.demand > usage.supply)
+            .cloned()
+            .map(RoutabilityWarning::TilePinDemandExceedsSupply),
+    );
+    warnings.extend(
+        sink_drivers
+            .iter()
+            .filter(|(_, drivers)| **drivers > 1)
+            .map(|(sink, drivers)| RoutabilityWarning::SinkDrivenByMultipleNets {
+                sink: (*sink).clone(),
+                drivers: *drivers,
+            }),
+    );
+    warnings.extend(
+        cut_lines
+            .iter()
+            .filter(|cut| cut.demand > cut.supply)
+            .cloned()
+            .map(RoutabilityWarning::CutLineOverloaded),
+    );
+
+    RoutabilityReport {
+        net_count: net_list.plan.len(),
+        connection_count: net_list.plan.iter().map(|net| net.sinks.len()).sum(),
+        fanout_histogram,
+        total_half_perimeter: nets.iter().map(|net| net.half_perimeter).sum(),
+        nets,
+        constant_nets,
+        tile_pin_usage,
+        cut_lines,
+        warnings,
+    }
+}
+
+/// Counts the nets and wires crossing every cut-line between two adjacent columns or rows
+fn cut_lines(fabric: &Fabric, nets: &[NetStatistics]) -> Vec<CutLine> {
+    let graph = &fabric.graph;
+    let (width, height) = graph
+        .nodes
+        .iter()
+        .fold((0, 0), |(width, height), node| (width.max(node.tile.0), height.max(node.tile.1)));
+
+    // A wire is a node, it crosses every cut between the tiles it connects, however many pips it drives
+    let mut vertical_supply = vec![0; usize::from(width)];
+    let mut horizontal_supply = vec![0; usize::from(height)];
+    for (start, edges) in graph.nodes.iter().zip(graph.map()) {
+        let tiles = edges.iter().map(|edge| graph.get_node(edge.node_id).tile);
+        let (left, right, bottom, top) = tiles.fold(
+            (start.tile.0, start.tile.0, start.tile.1, start.tile.1),
+            |(left, right, bottom, top), tile| (left.min(tile.0), right.max(tile.0), bottom.min(tile.1), top.max(tile.1)),
+        );
+        vertical_supply[usize::from(left)..usize::from(right)]
+            .iter_mut()
+            .for_each(|supply| *supply += 1);
+        horizontal_supply[usize::from(bottom)..usize::from(top)]
+            .iter_mut()
+            .for_each(|supply| *supply += 1);
+    }
+
+    let vertical = (0..width).map(|x| CutLine {
+        direction: CutDirection::Vertical,
+        position: x,
+        demand: nets.iter().filter(|net| net.bounding_box.crosses_vertical_cut(x)).count(),
+        supply: vertical_supply[usize::from(x)],
+    });
+    let horizontal = (0..height).map(|y| CutLine {
+        direction: CutDirection::Horizontal,
+        position: y,
+        demand: nets
+            .iter()
+            .filter(|net| net.bounding_box.crosses_horizontal_cut(y))
+            .count(),
+        supply: horizontal_supply[usize::from(y)],
+    });
+    vertical.chain(horizontal).collect()
+}
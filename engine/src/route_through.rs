@@ -0,0 +1,150 @@
+//! Module `route_through`
+//!
+//! Virtual edges through free LUTs. A LUT configured as a buffer forwards one input to its output,
+//! which lets a connection escape saturated routing muxes at a high cost.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    Fabric, FabricResult,
+    fabric::node::{Node, NodeId, NodeType},
+    netlist::NetListInternal,
+};
+
+/// Default of [`crate::RoutingConfigBuilder::lut_route_through_cost`]
+pub const DEFAULT_ROUTE_THROUGH_COST: f32 = 10.0;
+
+/// Returns `true` if the edge `start` -> `end` forwards an input of a LUT to its output
+#[must_use]
+pub fn is_route_through(start: &Node, end: &Node) -> bool {
+    match (&start.typ, &end.typ) {
+        (NodeType::LutInput(input_bel), NodeType::LutOutput(output_bel)) => input_bel == output_bel && start.tile == end.tile,
+        _ => false,
+    }
+}
+
+/// Adds an edge with the base cost `cost` from every input of the free LUTs of the fabric to
+/// their output. Edges already in the graph are kept, so adding them again has no effect.
+///
+/// Returns the number of added edges.
+pub fn add_route_throughs(fabric: &mut Fabric, cost: f32) -> usize {
+    let graph = &mut fabric.graph;
+    let mut added = Vec::new();
+    for (tile, inputs, output) in fabric.tile_manager.route_through_luts() {
+        let Some(output) = graph.get_node_id(&format!("{tile}.{output}")).copied() else {
+            continue;
+        };
+        for input in inputs {
+            let Some(input) = graph.get_node_id(&format!("{tile}.{input}")).copied() else {
+                continue;
+            };
+            if graph.map().find(input, output).is_none() {
+                added.push((input, output, cost));
+            }
+        }
+    }
+    graph.add_edges(&added);
+    added.len()
+}
+
+/// Removes the route-through edges of LUTs that are no longer free, e.g. borrowed as constant
+/// drivers after the edges were added. Returns the number of removed edges.
+pub fn prune_route_throughs(fabric: &mut Fabric) -> usize {
+    let free_outputs = fabric
+        .tile_manager
+        .route_through_luts()
+        .into_iter()
+        .filter_map(|(tile, _, output)| fabric.graph.get_node_id(&format!("{tile}.{output}")).copied())
+        .collect::<BTreeSet<NodeId>>();
+    let graph = &fabric.graph;
+    let removed = graph
+        .map()
+        .iter()
+        .enumerate()
+        .flat_map(|(start, edges)| edges.iter().map(move |edge| (NodeId::new(start), edge.node_id)))
+        .filter(|(start, end)| is_route_through(&graph.nodes[*start], &graph.nodes[*end]) && !free_outputs.contains(end))
+        .collect::<BTreeSet<(NodeId, NodeId)>>();
+    if removed.is_empty() {
+        return 0;
+    }
+    fabric.graph.retain_edges(|start, end| !removed.contains(&(start, end)))
+}
+
+/// Borrows every LUT a routed net of `net_list` passes through as a buffer of the entered input,
+/// see [`crate::TileManager::borrow_route_through`].
+///
+/// Returns the number of borrowed LUTs.
+///
+/// # Errors
+/// Fails if a route-through enters a LUT input the `TileManager` does not know
+pub fn claim_route_throughs(fabric: &mut Fabric, net_list: &NetListInternal) -> FabricResult<usize> {
+    let mut entered = BTreeMap::new();
+    let paths = net_list
+        .plan
+        .iter()
+        .filter_map(|net| net.result.as_ref())
+        .flat_map(|result| result.paths.values());
+    for pair in paths.flat_map(|path| path.windows(2)) {
+        let (start, end) = (fabric.graph.get_node(pair[0]), fabric.graph.get_node(pair[1]));
+        if let NodeType::LutInput(bel_index) = start.typ
+            && is_route_through(start, end)
+        {
+            entered.entry((start.tile, bel_index)).or_insert_with(|| start.id.clone());
+        }
+    }
+    let mut claimed = 0;
+    for ((tile, bel_index), input) in entered {
+        if fabric.tile_manager.borrow_route_through(tile, bel_index, &input)? {
+            claimed += 1;
+        }
+    }
+    Ok(claimed)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{FabricGraph, LutInit, NetInternal, NetResultInternal, TileManager, fabric::node::TileId};
+    use testing_utils::get_test_data_path;
+
+    #[test]
+    fn test_claim_route_through() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let mut fabric = Fabric::new(graph, tile_manager);
+        let added = add_route_throughs(&mut fabric, DEFAULT_ROUTE_THROUGH_COST);
+        assert!(added > 0);
+        assert_eq!(add_route_throughs(&mut fabric, DEFAULT_ROUTE_THROUGH_COST), 0);
+
+        let node = |id: &str| *fabric.graph.get_node_id(id).unwrap();
+        let (input, output) = (node("X1Y1.LA_I2"), node("X1Y1.LA_O"));
+        assert!(is_route_through(fabric.graph.get_node(input), fabric.graph.get_node(output)));
+        assert!((fabric.graph.get_edge(input, output).unwrap().cost - DEFAULT_ROUTE_THROUGH_COST).abs() < f32::EPSILON);
+
+        let path = vec![input, output];
+        let net_list = NetListInternal {
+            plan: vec![NetInternal {
+                signal: input,
+                sinks: vec![output],
+                result: Some(NetResultInternal {
+                    paths: [(output, path.clone())].into(),
+                    nodes: path.iter().copied().collect::<BTreeSet<_>>(),
+                }),
+                intermediate_nodes: None,
+                bounding_box: None,
+            }],
+            unroutable: Vec::new(),
+        };
+        assert_eq!(claim_route_throughs(&mut fabric, &net_list).unwrap(), 1);
+        assert_eq!(
+            fabric.tile_manager.generate_init_fasm(),
+            vec![LutInit::identity(4, 2).to_fasm(TileId(1, 1), 'A')]
+        );
+        // The LUT is no longer free, so its route-through edges are pruned
+        assert_eq!(prune_route_throughs(&mut fabric), 4);
+        assert!(fabric.graph.get_edge(input, output).is_err());
+        assert!(fabric.graph.map_reversed()[output].is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Logically equivalent LUT inputs of the sinks of a net.
+//!
+//! A sink on a LUT input may land on any free input of the same LUT, the LUT function is permuted
+//! accordingly. The solvers search a path to any node of the sink set, the reached input replaces
+//! the sink in [`commit_sinks`].
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Fabric, FabricError, FabricGraph, FabricResult,
+    fabric::node::{Node, NodeId, NodeType},
+    netlist::NetInternal,
+};
+
+/// Maps every sink of `net` to the set of nodes it may be routed to.
+///
+/// The first sink of a LUT gets the free inputs of that LUT in addition to its own pin, every other
+/// sink only its own node. Only reads the free LUT inputs.
+pub fn equivalent_sinks(fabric: &Fabric, net: &NetInternal) -> FabricResult<HashMap<NodeId, HashSet<NodeId>>> {
+    let mut seen_luts = HashSet::new();
+    net.sinks
+        .iter()
+        .map(|sink| {
+            let sink_node = fabric.graph.get_node(*sink);
+            let mut sinks_free = match sink_node.typ {
+                NodeType::LutInput(bel_index) if seen_luts.insert((sink_node.tile, bel_index)) => {
+                    let free_lut_inputs = fabric.tile_manager.free_lut_inputs(sink_node.tile, bel_index)?;
+                    lut_input_ids(&fabric.graph, sink_node, &free_lut_inputs)?
+                }
+                _ => HashSet::new(),
+            };
+            sinks_free.insert(*sink);
+            Ok((*sink, sinks_free))
+        })
+        .collect()
+}
+
+/// Node ids of the LUT `inputs` in the tile of `sink_node`
+pub fn lut_input_ids(graph: &FabricGraph, sink_node: &Node, inputs: &[String]) -> FabricResult<HashSet<NodeId>> {
+    inputs
+        .iter()
+        .map(|sink_id_str| format!("{}.{}", sink_node.tile, sink_id_str))
+        .map(|a| {
+            graph
+                .get_node_id(&a)
+                .copied()
+                .ok_or(FabricError::InvalidStringNodeId(a))
+        })
+        .collect()
+}
+
+/// Error of a failed search from `start` to any node of the sink set `sinks`
+pub fn search_failed(graph: &FabricGraph, start: NodeId, sinks: &HashSet<NodeId>) -> FabricError {
+    FabricError::FindOnePathfindingFailed {
+        start: start.as_node(graph),
+        sink: sinks.iter().map(|sink| graph.get_node(*sink).clone()).collect(),
+    }
+}
+
+/// Claims the LUT inputs reached by the result of `net` and frees the replaced ones.
+/// The reached inputs become the new sinks of the net.
+pub fn commit_sinks(fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
+    let Some(result) = &net.result else {
+        return Ok(());
+    };
+    for sink in net.sinks.iter().filter(|sink| !result.paths.contains_key(sink)) {
+        let node = fabric.graph.get_node(*sink);
+        if let NodeType::LutInput(bel_index) = &node.typ {
+            fabric.tile_manager.free_lut_input(node.tile, *bel_index, &node.id)?;
+        }
+    }
+    for sink in result.paths.keys() {
+        let node = fabric.graph.get_node(*sink);
+        if let NodeType::LutInput(bel_index) = &node.typ {
+            fabric.tile_manager.mark_lut_input_used(node.tile, *bel_index, &node.id)?;
+        }
+    }
+    net.sinks = result.paths.keys().copied().collect::<Vec<NodeId>>();
+    Ok(())
+}
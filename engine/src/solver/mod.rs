@@ -1,7 +1,10 @@
+mod equivalent_sinks;
+mod route_tree;
 mod simple;
 mod simple_steiner;
 mod steiner;
 
+pub use route_tree::RouteTreeSolver;
 pub use simple::SimpleSolver;
 pub use simple_steiner::SimpleSteinerSolver;
 pub use steiner::SteinerSolver;
@@ -12,7 +15,8 @@ use crate::{Fabric, FabricResult, NetInternal};
 ///
 /// Implementors of this trait can define how individual signals are routed
 /// and how the global routing plan is prepared before execution.
-pub trait RouteNet {
+/// Solvers are shared by the threads routing a batch of nets, see [`Self::solve_shared`].
+pub trait RouteNet: Sync {
     /// Executes the routing algorithm for a single net.
     ///
     /// This method is responsible for finding a path in the [`FabricGraph`]
@@ -24,6 +28,28 @@ pub trait RouteNet {
     /// given the current graph constraints.
     fn solve(&self, graph: &mut Fabric, routing: &mut NetInternal) -> FabricResult<()>;
 
+    /// Executes the routing algorithm for a single net without modifying the fabric.
+    ///
+    /// The path finder calls this for the nets of a batch in parallel, all of them see the same
+    /// read-only `Fabric`. Side effects on the fabric are applied afterwards by [`Self::commit`].
+    /// Returns `None` if the solver needs mutable access, the net is then routed with [`Self::solve`].
+    ///
+    /// # Errors
+    ///
+    /// The same as [`Self::solve`]
+    fn solve_shared(&self, _fabric: &Fabric, _routing: &mut NetInternal) -> Option<FabricResult<()>> {
+        None
+    }
+
+    /// Applies the changes of a successful [`Self::solve_shared`] to the fabric, e.g. claiming LUT inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fabric cannot be updated
+    fn commit(&self, _fabric: &mut Fabric, _routing: &mut NetInternal) -> FabricResult<()> {
+        Ok(())
+    }
+
     /// Prepares the graph or the route plan before the main solving phase.
     ///
     /// This is typically used for global optimizations, such as pre-calculating
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    Fabric, FabricError, FabricResult, RouteNet,
+    fabric::node::NodeId,
+    netlist::{NetInternal, NetResultInternal},
+};
+
+/// Grows the route tree of a net sink by sink.
+///
+/// Every search starts from all nodes of the partial tree with the current congestion costs, so a
+/// sink branches off the tree wherever that is cheapest. Critical sinks are connected first and the
+/// tree nodes are seeded with their delay from the source weighted by the criticality of the sink.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct RouteTreeSolver;
+
+/// Node of a partial route tree
+#[derive(Debug, Clone, Copy)]
+struct TreeNode {
+    parent: Option<NodeId>,
+    /// Sum of the base costs from the source
+    delay: f32,
+}
+
+impl RouteNet for RouteTreeSolver {
+    fn pre_process(&self, _graph: &mut Fabric, _route_plan: &mut [NetInternal]) -> FabricResult<()> {
+        Ok(())
+    }
+    fn solve(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
+        grow_route_tree(fabric, net)
+    }
+    fn solve_shared(&self, fabric: &Fabric, net: &mut NetInternal) -> Option<FabricResult<()>> {
+        Some(grow_route_tree(fabric, net))
+    }
+
+    fn identifier(&self) -> &'static str {
+        "Route Tree Solver"
+    }
+}
+
+/// Routes `net` by growing its route tree, only reads the fabric
+fn grow_route_tree(fabric: &Fabric, net: &mut NetInternal) -> FabricResult<()> {
+    let signal = net.signal;
+    let graph = &fabric.graph;
+    let criticality = |sink: NodeId| {
+        fabric
+            .slack_report
+            .as_ref()
+            .map_or(0.0, |slack_report| *slack_report.criticalities.get(&(signal, sink)).unwrap_or(&0.0))
+    };
+    let source_tile = graph.get_node(signal).tile;
+    let distance = |sink: NodeId| {
+        let tile = graph.get_node(sink).tile;
+        u16::from(tile.0.abs_diff(source_tile.0)) + u16::from(tile.1.abs_diff(source_tile.1))
+    };
+
+    let mut sinks = net.sinks.clone();
+    sinks.sort_by(|a, b| {
+        criticality(*b)
+            .total_cmp(&criticality(*a))
+            .then_with(|| distance(*a).cmp(&distance(*b)))
+            .then_with(|| a.cmp(b))
+    });
+
+    let mut tree = BTreeMap::from([(signal, TreeNode { parent: None, delay: 0.0 })]);
+    for sink in &sinks {
+        let criticality = criticality(*sink);
+        let sources = tree
+            .iter()
+            .map(|(node, tree_node)| (*node, criticality * tree_node.delay))
+            .collect::<Vec<(NodeId, f32)>>();
+        let (path, _cost) = graph
+            .find_path_from(&sources, *sink, &fabric.search_options(net.bounding_box, criticality))
+            .ok_or_else(|| FabricError::PathfindingFailed {
+                start: graph.get_node(signal).clone(),
+                sink: graph.get_node(*sink).clone(),
+            })?;
+        for pair in path.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let delay = tree[&from].delay + graph.get_edge(from, to)?.cost;
+            tree.entry(to).or_insert(TreeNode {
+                parent: Some(from),
+                delay,
+            });
+        }
+    }
+
+    let paths = sinks
+        .iter()
+        .map(|sink| {
+            let mut path = vec![*sink];
+            while let Some(parent) = tree[path.last().unwrap()].parent {
+                path.push(parent);
+            }
+            path.reverse();
+            (*sink, path)
+        })
+        .collect::<BTreeMap<NodeId, Vec<NodeId>>>();
+    let nodes = tree.into_keys().collect::<BTreeSet<NodeId>>();
+    net.result = Some(NetResultInternal { paths, nodes });
+    Ok(())
+}
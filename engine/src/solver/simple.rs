@@ -1,11 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::{
-    Fabric, FabricError, FabricResult, RouteNet,
-    fabric::node::{Node, NodeId, NodeType},
+    Fabric, FabricResult, RouteNet,
+    fabric::node::{NodeId, NodeType},
     netlist::{NetInternal, NetResultInternal},
+    solver::equivalent_sinks::{commit_sinks, equivalent_sinks, search_failed},
 };
 
 #[derive(Eq, PartialEq, Debug, Clone)]
@@ -15,75 +16,107 @@ impl RouteNet for SimpleSolver {
     fn pre_process(&self, _graph: &mut Fabric, _route_plan: &mut [NetInternal]) -> FabricResult<()> {
         Ok(())
     }
+    /// Claims the LUT inputs only once a path to every sink was found, a failed search leaves the
+    /// free inputs untouched.
     fn solve(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
-        let signal = net.signal;
-        let sinks = net
-            .sinks
-            .iter()
-            .filter_map(|sink| {
-                let sink_node = fabric.graph.get_node(*sink);
-                if let NodeType::LutInput(bel_index) = sink_node.typ {
-                    return Some((sink, sink_node, bel_index));
-                }
-                None
-            })
-            .map(|(sink, sink_node, bel_index)| {
-                let free_lut_inputs = fabric.tile_manager.get_free_lut_inputs(sink_node.tile, bel_index)?;
-                let mut sinks_free = free_lut_inputs
-                    .iter()
-                    .map(|sink_id_str| format!("{}.{}", sink_node.tile, sink_id_str))
-                    .map(|a| {
-                        fabric
-                            .graph
-                            .get_node_id(&a)
-                            .copied()
-                            .ok_or(FabricError::InvalidStringNodeId(a))
-                    })
-                    .collect::<FabricResult<HashSet<NodeId>>>()?;
-                sinks_free.insert(*sink);
-
-                Ok((*sink, sinks_free))
-            })
-            .collect::<FabricResult<HashMap<NodeId, HashSet<NodeId>>>>()?;
+        self.solve_shared(fabric, net).unwrap_or(Ok(()))?;
+        commit_sinks(fabric, net)
+    }
 
-        let paths: HashMap<NodeId, Vec<NodeId>> = sinks
-            .par_iter()
-            .map(|(current_sink, sinks)| {
-                let criticality = fabric
-                    .slack_report
-                    .as_ref()
-                    .map_or(0.0, |a| *a.criticalities.get(&(signal, *current_sink)).unwrap_or(&0.0));
-                let (node_found, path, _cost) = fabric.graph.dijkstra_find_one(signal, sinks, criticality).ok_or_else(|| {
-                    FabricError::FindOnePathfindingFailed {
-                        start: signal.as_node(&fabric.graph),
-                        sink: sinks
-                            .iter()
-                            .map(|sink| fabric.graph.get_node(*sink).clone())
-                            .collect::<HashSet<Node>>(),
-                    }
-                })?;
-                Ok((node_found, path))
-            })
-            .collect::<FabricResult<HashMap<NodeId, Vec<NodeId>>>>()?;
+    /// Searches the paths and only reads the free LUT inputs. The first sink of a LUT may swap to
+    /// any free input of it, the chosen inputs are claimed by [`Self::commit`].
+    fn solve_shared(&self, fabric: &Fabric, net: &mut NetInternal) -> Option<FabricResult<()>> {
+        Some(equivalent_sinks(fabric, net).and_then(|mut sinks| {
+            sinks.retain(|sink, _| matches!(fabric.graph.get_node(*sink).typ, NodeType::LutInput(_)));
+            let paths = find_sink_paths(fabric, net, &sinks)?;
+            let nodes = paths.values().flatten().copied().collect::<BTreeSet<NodeId>>();
+            net.result = Some(NetResultInternal { paths, nodes });
+            Ok(())
+        }))
+    }
 
-        let nodes = paths.values().flatten().copied().collect::<HashSet<NodeId>>();
-        net.sinks = paths.keys().copied().collect::<Vec<NodeId>>();
-        //Now we need to unmark the unused inputs of the luts.
-        sinks
-            .into_values()
-            .flatten()
-            .filter(|a| !paths.contains_key(a))
-            .for_each(|a| {
-                let node = fabric.graph.get_node(a);
-                if let NodeType::LutInput(bel_index) = &node.typ {
-                    fabric.tile_manager.free_lut_input(node.tile, *bel_index, &node.id).unwrap();
-                }
-            });
-        net.result = Some(NetResultInternal { paths, nodes });
-        Ok(())
+    /// Claims the LUT inputs chosen by [`Self::solve_shared`] and frees the replaced ones.
+    fn commit(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
+        commit_sinks(fabric, net)
     }
 
     fn identifier(&self) -> &'static str {
         "Simple Solver"
     }
 }
+
+/// Searches a path from the signal of `net` to one of the equivalent inputs of every sink
+fn find_sink_paths(
+    fabric: &Fabric,
+    net: &NetInternal,
+    sinks: &HashMap<NodeId, HashSet<NodeId>>,
+) -> FabricResult<BTreeMap<NodeId, Vec<NodeId>>> {
+    let signal = net.signal;
+    sinks
+        .par_iter()
+        .map(|(current_sink, sinks)| {
+            let criticality = fabric
+                .slack_report
+                .as_ref()
+                .map_or(0.0, |a| *a.criticalities.get(&(signal, *current_sink)).unwrap_or(&0.0));
+            let (node_found, path, _cost) = fabric
+                .graph
+                .find_one(signal, sinks, &fabric.search_options(net.bounding_box, criticality))
+                .ok_or_else(|| search_failed(&fabric.graph, signal, sinks))?;
+            Ok((node_found, path))
+        })
+        .collect::<FabricResult<BTreeMap<NodeId, Vec<NodeId>>>>()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        DEFAULT_CONSTANT_SEARCH_RADIUS, FabricGraph, NetListInternal, TileManager,
+        fabric::{bounding_box::BoundingBox, node::Node},
+        generator::{Fanout, TestConfig, generate_test},
+    };
+    use testing_utils::get_test_data_path;
+
+    #[test]
+    fn test_failed_solve_keeps_free_lut_inputs() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let mut fabric = Fabric::new(graph, tile_manager);
+        let test_config = TestConfig {
+            seed: 5,
+            percentage: 0.2,
+            fanout: Fanout::Fixed(3),
+            ..TestConfig::default()
+        };
+        let external = generate_test(&fabric.graph, &test_config).unwrap();
+        let mut net_list = NetListInternal::from_external(&fabric.graph, &external).unwrap();
+        fabric.check_pathing(&mut net_list, DEFAULT_CONSTANT_SEARCH_RADIUS, false).unwrap();
+        let signal_tile = |net: &NetInternal| fabric.graph.get_node(net.signal).tile;
+        let mut net = net_list
+            .plan
+            .iter()
+            .find(|net| net.sinks.iter().any(|sink| fabric.graph.get_node(*sink).tile != signal_tile(net)))
+            .unwrap()
+            .clone();
+        let sinks = net.sinks.clone();
+        let free_inputs = |fabric: &Fabric| {
+            sinks
+                .iter()
+                .filter_map(|sink| match fabric.graph.get_node(*sink) {
+                    node @ Node { typ: NodeType::LutInput(bel_index), .. } => {
+                        Some(fabric.tile_manager.free_lut_inputs(node.tile, *bel_index).unwrap().len())
+                    }
+                    _ => None,
+                })
+                .sum::<usize>()
+        };
+        let before = free_inputs(&fabric);
+        assert!(before > 0);
+
+        // A box around the source tile keeps the sinks in other tiles out of reach
+        net.bounding_box = Some(BoundingBox::new(signal_tile(&net)));
+        assert!(SimpleSolver.solve(&mut fabric, &mut net).is_err());
+        assert_eq!(free_inputs(&fabric), before);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
 };
 
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
@@ -9,6 +9,7 @@ use crate::{
     Fabric, FabricError, FabricGraph, FabricResult, RouteNet,
     fabric::node::NodeId,
     netlist::{NetInternal, NetResultInternal},
+    solver::equivalent_sinks::{commit_sinks, equivalent_sinks, search_failed},
 };
 
 #[derive(Eq, PartialEq, Debug, Clone)]
@@ -49,32 +50,14 @@ impl RouteNet for SimpleSteinerSolver {
         Ok(())
     }
     fn solve(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
-        if let Some(steiner_tree) = &net.intermediate_nodes {
-            let mut paths = HashMap::new();
-            let mut nodes = HashSet::new();
-            for (terminal, route) in steiner_tree {
-                let criticallity = fabric.slack_report.as_ref().map_or(0.0, |slack_report| {
-                    *slack_report.criticalities.get(&(net.signal, *terminal)).unwrap_or(&0.0)
-                });
-                let mut path = Vec::new();
-                for steiner_node in route.windows(2) {
-                    let (start, end) = (steiner_node[0], steiner_node[1]);
-                    let Some((a, _b)) = fabric.graph.dijkstra(start, end, criticallity) else {
-                        let start_name = fabric.graph.get_node(start).id();
-                        let end_name = fabric.graph.get_node(end).id();
-                        return Err(format!("Could not find path between steiner nodes: {start_name}->{end_name}").into());
-                    };
-                    nodes.extend(&a);
-                    path.extend(&a[..a.len() - 1]);
-                }
-                path.push(*terminal);
-                paths.insert(*terminal, path);
-            }
-            net.result = Some(NetResultInternal { paths, nodes });
-            Ok(())
-        } else {
-            Err("No steiner Tree precalculated.".into())
-        }
+        route_steiner_tree(fabric, net)?;
+        commit_sinks(fabric, net)
+    }
+    fn solve_shared(&self, fabric: &Fabric, net: &mut NetInternal) -> Option<FabricResult<()>> {
+        Some(route_steiner_tree(fabric, net))
+    }
+    fn commit(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
+        commit_sinks(fabric, net)
     }
 
     fn identifier(&self) -> &'static str {
@@ -82,6 +65,72 @@ impl RouteNet for SimpleSteinerSolver {
     }
 }
 
+/// Routes `net` along its precalculated Steiner tree, only reads the fabric.
+///
+/// The last segment of every terminal may end on an equivalent LUT input, the Steiner tree is then
+/// moved to the reached input.
+fn route_steiner_tree(fabric: &Fabric, net: &mut NetInternal) -> FabricResult<()> {
+    if let Some(steiner_tree) = &net.intermediate_nodes {
+        let sink_sets = equivalent_sinks(fabric, net)?;
+        let mut paths = BTreeMap::new();
+        let mut nodes = BTreeSet::new();
+        let mut moved = Vec::new();
+        for (terminal, route) in steiner_tree {
+            let criticallity = fabric.slack_report.as_ref().map_or(0.0, |slack_report| {
+                *slack_report.criticalities.get(&(net.signal, *terminal)).unwrap_or(&0.0)
+            });
+            let options = fabric.search_options(net.bounding_box, criticallity);
+            let mut path = Vec::new();
+            let mut reached = *terminal;
+            for (i, steiner_node) in route.windows(2).enumerate() {
+                let (start, end) = (steiner_node[0], steiner_node[1]);
+                // A failed segment is reported as a failed search for the terminal it leads to
+                let found = if i + 2 == route.len()
+                    && let Some(sink_set) = sink_sets.get(terminal)
+                {
+                    fabric
+                        .graph
+                        .find_one(start, sink_set, &options)
+                        .map(|(node, a, _cost)| {
+                            reached = node;
+                            a
+                        })
+                        .ok_or_else(|| search_failed(&fabric.graph, start, sink_set))
+                } else {
+                    fabric
+                        .graph
+                        .find_path(start, end, &options)
+                        .map(|(a, _cost)| a)
+                        .ok_or_else(|| FabricError::PathfindingFailed {
+                            start: start.as_node(&fabric.graph),
+                            sink: terminal.as_node(&fabric.graph),
+                        })
+                };
+                let a = found?;
+                nodes.extend(&a);
+                path.extend(&a[..a.len() - 1]);
+            }
+            path.push(reached);
+            paths.insert(reached, path);
+            if reached != *terminal {
+                moved.push((*terminal, reached));
+            }
+        }
+        if let Some(steiner_tree) = &mut net.intermediate_nodes {
+            for (terminal, reached) in moved {
+                if let Some(mut route) = steiner_tree.remove(&terminal) {
+                    *route.last_mut().unwrap() = reached;
+                    steiner_tree.insert(reached, route);
+                }
+            }
+        }
+        net.result = Some(NetResultInternal { paths, nodes });
+        Ok(())
+    } else {
+        Err("No steiner Tree precalculated.".into())
+    }
+}
+
 fn pre_calc_steiner_tree(graph: &mut FabricGraph, net: &NetInternal) -> FabricResult<HashMap<NodeId, Vec<NodeId>>> {
     let dists = net
         .sinks
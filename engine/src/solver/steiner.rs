@@ -1,15 +1,30 @@
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
 };
 
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
-use crate::{Fabric, FabricResult, RouteNet, fabric::node::NodeId, netlist::{NetInternal, NetResultInternal}};
+use crate::{
+    Fabric, FabricError, FabricResult, RouteNet,
+    fabric::node::NodeId,
+    netlist::{NetInternal, NetResultInternal},
+    solver::equivalent_sinks::{commit_sinks, equivalent_sinks, search_failed},
+};
 
+/// Connects the sinks of a net along the cheapest approximated Steiner tree.
+///
+/// With a `SlackReport`, sinks with a criticality of at least [`Self::CRITICAL_THRESHOLD`] get a
+/// direct path from the source. The remaining sinks share the tree and are searched with their own
+/// criticality. Sinks on LUT inputs may swap to any free input of their LUT.
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct SteinerSolver;
 
+impl SteinerSolver {
+    /// Criticality from which a sink is routed directly from the source instead of the tree
+    pub const CRITICAL_THRESHOLD: f32 = 0.9;
+}
+
 #[derive(Debug, Clone)]
 struct SteinerCandidate {
     base_path: Vec<NodeId>,
@@ -25,100 +40,205 @@ impl RouteNet for SteinerSolver {
         Ok(())
     }
     fn solve(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
-        let dists = net
-            .sinks
-            .par_iter()
-            .map(|sink| (*sink, fabric.graph.dijkstra_all(*sink)))
-            .collect::<HashMap<NodeId, Vec<f32>>>();
-        let signal = net.signal;
-        let base_paths: Vec<(NodeId, NodeId)> = net.sinks.iter().map(|&sink| (signal, sink)).collect();
-
-        // 1. Parallel reduction to find the single best SteinerCandidate
-        let best_candidate: Result<SteinerCandidate, String> = base_paths
-            .into_par_iter()
-            .map(|(start, base_sink)| {
-                // --- Computation to find the MINIMUM COST ---
-                // Calculate the cost of the base path (Dijkstra is still necessary here)
-                let Some((base_path, mut costs)) = fabric.graph.dijkstra(start, base_sink, 0.0) else {
-                    let start_name = fabric.graph.get_node(start).id();
-                    let base_sink_name = fabric.graph.get_node(base_sink).id();
-                    return Err(format!("Could not find a base path start: {start_name}, base sink: {base_sink_name}"));
-                };
-
-                // Calculate the cost of connecting all other sinks to this base path
-                let mid_points = net
-                    .sinks
-                    .iter()
-                    .map(|sink| {
-                        let Some(terminal_distances) = dists.get(sink) else {
-                            let sink_name = fabric.graph.get_node(*sink).id();
-                            return Err(format!("No precalculated distances for the sink: {sink_name}"));
-                        };
-
-                        // Find the connection node (min_node) on the base_path
-                        let (min_node, cost_to_base_path) = base_path
-                            .iter()
-                            .map(|&node| (node, terminal_distances[node]))
-                            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Greater))
-                            .unwrap();
-
-                        // This cost is the *shortest path cost* from the base path to the sink.
-                        costs += cost_to_base_path;
-                        Ok((*sink, min_node))
-                    })
-                    .collect::<Result<HashMap<NodeId, NodeId>, String>>();
-                match mid_points {
-                    Ok(mid_points) => Ok(SteinerCandidate {
-                        base_path,
-                        mid_points,
-                        costs,
-                    }),
-                    Err(err) => Err(err),
-                }
-            })
-            // 2. Reduce the candidates to find the one with the minimum cost.
-            .reduce(
-                || Err("No minmum".to_string()),
-                |acc, item| match (acc, item) {
-                    (Err(err1), Err(err2)) => Err(format!("err: {err1}\n err: {err2}\n")),
-                    (Ok(current_best), Err(_err)) => Ok(current_best),
-                    (Err(_err), Ok(item)) => Ok(item),
-                    (Ok(current_best), Ok(item)) => {
-                        if item.costs < current_best.costs {
-                            Ok(item)
-                        } else {
-                            Ok(current_best)
-                        }
-                    }
-                },
-            );
-
-        // 3. Final Calculation: Sequentially calculate the full result for the winner.
-        if let Ok(best_candidate) = best_candidate {
-            let mut nodes = HashSet::new();
-            nodes.extend(&best_candidate.base_path);
-
-            let mut paths = HashMap::new();
-
-            for (sink, mid_point) in &best_candidate.mid_points {
-                let Some((mut path_to_mid, _cost)) = fabric.graph.dijkstra(signal, *mid_point, 0.0) else {
-                    let sink_name = fabric.graph.get_node(*sink).id();
-                    return Err(format!("Could not find a route for sink: {sink_name}").into());
-                };
-                let Some((path_from_mid, _cost)) = fabric.graph.dijkstra(*mid_point, *sink, 0.0) else {
-                    let sink_name = fabric.graph.get_node(*sink).id();
-                    return Err(format!("Could not find a route for sink: {sink_name}").into());
-                };
-                nodes.extend(&path_from_mid);
-                path_to_mid.extend(&path_from_mid[1..]);
-                paths.insert(*sink, path_to_mid);
+        route_steiner(fabric, net)?;
+        commit_sinks(fabric, net)
+    }
+    fn solve_shared(&self, fabric: &Fabric, net: &mut NetInternal) -> Option<FabricResult<()>> {
+        Some(route_steiner(fabric, net))
+    }
+    fn commit(&self, fabric: &mut Fabric, net: &mut NetInternal) -> FabricResult<()> {
+        commit_sinks(fabric, net)
+    }
+}
+
+/// Routes `net` along the cheapest Steiner candidate, only reads the fabric
+fn route_steiner(fabric: &Fabric, net: &mut NetInternal) -> FabricResult<()> {
+    let signal = net.signal;
+    let criticality = |sink: NodeId| sink_criticality(fabric, signal, sink);
+    let options = |sink: NodeId| fabric.search_options(net.bounding_box, criticality(sink));
+    let (critical, shared): (Vec<NodeId>, Vec<NodeId>) = net
+        .sinks
+        .iter()
+        .partition(|sink| criticality(**sink) >= SteinerSolver::CRITICAL_THRESHOLD);
+    let sink_sets = equivalent_sinks(fabric, net)?;
+
+    let mut paths = route_direct(fabric, net, &critical, &sink_sets)?;
+    let mut nodes = paths.values().flatten().copied().collect::<BTreeSet<NodeId>>();
+    if shared.is_empty() && !critical.is_empty() {
+        net.result = Some(NetResultInternal { paths, nodes });
+        return Ok(());
+    }
+
+    let dists = shared
+        .par_iter()
+        .map(|sink| {
+            let ends = sink_sets[sink].iter().copied().collect::<Vec<NodeId>>();
+            (*sink, fabric.graph.reverse_costs(&ends, criticality(*sink)))
+        })
+        .collect::<HashMap<NodeId, Vec<f32>>>();
+    let base_paths: Vec<(NodeId, NodeId)> = shared.iter().map(|&sink| (signal, sink)).collect();
+
+    // 1. Parallel reduction to find the single best SteinerCandidate
+    let best_candidate: FabricResult<SteinerCandidate> = base_paths
+        .into_par_iter()
+        .map(|(start, base_sink)| {
+            // --- Computation to find the MINIMUM COST ---
+            // Calculate the cost of the base path (Dijkstra is still necessary here)
+            let Some((_, base_path, mut costs)) = fabric.graph.find_one(start, &sink_sets[&base_sink], &options(base_sink)) else {
+                return Err(search_failed(&fabric.graph, start, &sink_sets[&base_sink]));
+            };
+
+            // Calculate the cost of connecting all other sinks to this base path
+            let mid_points = shared
+                .iter()
+                .map(|sink| {
+                    let Some(terminal_distances) = dists.get(sink) else {
+                        let sink_name = fabric.graph.get_node(*sink).id();
+                        return Err(FabricError::Other(format!("No precalculated distances for the sink: {sink_name}")));
+                    };
+
+                    // Find the connection node (min_node) on the base_path
+                    let (min_node, cost_to_base_path) = base_path
+                        .iter()
+                        .map(|&node| (node, terminal_distances[node]))
+                        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Greater))
+                        .unwrap();
+
+                    // This cost is the *shortest path cost* from the base path to the sink.
+                    costs += cost_to_base_path;
+                    Ok((*sink, min_node))
+                })
+                .collect::<FabricResult<HashMap<NodeId, NodeId>>>();
+            match mid_points {
+                Ok(mid_points) => Ok(SteinerCandidate {
+                    base_path,
+                    mid_points,
+                    costs,
+                }),
+                Err(err) => Err(err),
             }
+        })
+        // 2. Reduce the candidates to find the one with the minimum cost. Without any candidate the
+        // first failed search is reported, the reduction keeps the order of the base paths.
+        .reduce(
+            || Err(FabricError::NoSteinerTreeFound),
+            |acc, item| match (acc, item) {
+                (Err(FabricError::NoSteinerTreeFound), Err(err)) | (Err(err), Err(_)) => Err(err),
+                (Ok(current_best), Err(_err)) => Ok(current_best),
+                (Err(_err), Ok(item)) => Ok(item),
+                (Ok(current_best), Ok(item)) => {
+                    // Equal costs are decided by the sink of the base path instead of the arrival order
+                    if item
+                        .costs
+                        .total_cmp(&current_best.costs)
+                        .then_with(|| item.base_path.last().cmp(&current_best.base_path.last()))
+                        .is_lt()
+                    {
+                        Ok(item)
+                    } else {
+                        Ok(current_best)
+                    }
+                }
+            },
+        );
 
-            net.result = Some(NetResultInternal { paths, nodes });
-            Ok(())
-        } else {
+    // 3. Final Calculation: Sequentially calculate the full result for the winner.
+    let best_candidate = match best_candidate {
+        Ok(best_candidate) => best_candidate,
+        Err(err) => {
             net.result = None; // No sinks found
-            Err("Error".into())
+            return Err(err);
         }
+    };
+    nodes.extend(&best_candidate.base_path);
+
+    for (sink, mid_point) in &best_candidate.mid_points {
+        let Some((mut path_to_mid, _cost)) = fabric.graph.find_path(signal, *mid_point, &options(*sink)) else {
+            return Err(FabricError::PathfindingFailed {
+                start: signal.as_node(&fabric.graph),
+                sink: sink.as_node(&fabric.graph),
+            });
+        };
+        let Some((reached, path_from_mid, _cost)) = fabric.graph.find_one(*mid_point, &sink_sets[sink], &options(*sink)) else {
+            return Err(search_failed(&fabric.graph, *mid_point, &sink_sets[sink]));
+        };
+        nodes.extend(&path_from_mid);
+        path_to_mid.extend(&path_from_mid[1..]);
+        paths.insert(reached, path_to_mid);
+    }
+
+    net.result = Some(NetResultInternal { paths, nodes });
+    Ok(())
+}
+
+/// Criticality of the connection from `signal` to `sink` in the last `SlackReport`
+fn sink_criticality(fabric: &Fabric, signal: NodeId, sink: NodeId) -> f32 {
+    fabric
+        .slack_report
+        .as_ref()
+        .map_or(0.0, |slack_report| *slack_report.criticalities.get(&(signal, sink)).unwrap_or(&0.0))
+}
+
+/// Routes every sink of `sinks` on its own cheapest path from the source to one of its `sink_sets`
+fn route_direct(
+    fabric: &Fabric,
+    net: &NetInternal,
+    sinks: &[NodeId],
+    sink_sets: &HashMap<NodeId, HashSet<NodeId>>,
+) -> FabricResult<BTreeMap<NodeId, Vec<NodeId>>> {
+    sinks
+        .iter()
+        .map(|sink| {
+            let options = fabric.search_options(net.bounding_box, sink_criticality(fabric, net.signal, *sink));
+            let Some((reached, path, _cost)) = fabric.graph.find_one(net.signal, &sink_sets[sink], &options) else {
+                return Err(search_failed(&fabric.graph, net.signal, &sink_sets[sink]));
+            };
+            Ok((reached, path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        DEFAULT_CONSTANT_SEARCH_RADIUS, FabricGraph, NetListInternal, SlackReport, TileManager,
+        generator::{Fanout, TestConfig, generate_test},
+    };
+    use testing_utils::get_test_data_path;
+
+    #[test]
+    fn test_critical_sinks_are_routed_directly() {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let mut fabric = Fabric::new(graph, tile_manager);
+        let test_config = TestConfig {
+            seed: 5,
+            percentage: 0.2,
+            fanout: Fanout::Fixed(3),
+            ..TestConfig::default()
+        };
+        let external = generate_test(&fabric.graph, &test_config).unwrap();
+        let mut net_list = NetListInternal::from_external(&fabric.graph, &external).unwrap();
+        fabric.check_pathing(&mut net_list, DEFAULT_CONSTANT_SEARCH_RADIUS, false).unwrap();
+        let mut net = net_list.plan[0].clone();
+        let critical = net.sinks[0];
+        fabric.slack_report = Some(SlackReport {
+            slacks: HashMap::new(),
+            criticalities: HashMap::from([((net.signal, critical), 1.0)]),
+            worst_slack: ((net.signal, critical), -1.0),
+        });
+
+        let sink_sets = equivalent_sinks(&fabric, &net).unwrap();
+        let (reached, direct, _cost) = fabric
+            .graph
+            .find_one(net.signal, &sink_sets[&critical], &fabric.search_options(None, 1.0))
+            .unwrap();
+        SteinerSolver.solve(&mut fabric, &mut net).unwrap();
+        let result = net.result.unwrap();
+        assert_eq!(result.paths[&reached], direct);
+        assert_eq!(result.paths.len(), net.sinks.len());
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeSet, HashSet, VecDeque};
 
 use crate::{
     netlist::NetListInternal,
@@ -41,7 +41,7 @@ pub fn validate(net_list: &NetListInternal, graph: &FabricGraph) -> Result<(), S
 }
 
 /// BFS restricted to `allowed` node set.
-fn is_reachable_within_set(graph: &FabricGraph, start: NodeId, target: NodeId, allowed: &HashSet<NodeId>) -> bool {
+fn is_reachable_within_set(graph: &FabricGraph, start: NodeId, target: NodeId, allowed: &BTreeSet<NodeId>) -> bool {
     if start == target {
         return true;
     }
@@ -56,7 +56,7 @@ fn is_reachable_within_set(graph: &FabricGraph, start: NodeId, target: NodeId, a
     queue.push_back(start);
 
     while let Some(u) = queue.pop_front() {
-        for edge in &graph.map[u] {
+        for edge in &graph.map()[u] {
             let v = edge.node_id;
 
             if !allowed.contains(&v) {
@@ -1,6 +1,17 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
-use router::{FabricGraph, Logging, RoutingConfigBuilder, SimpleSteinerSolver, SteinerSolver, TileManager, create_fasm, create_test, route};
+use router::{
+    BoundingBox, CostModel, FabricError, NetOrdering, RouteNet, RouteTreeSolver, SimpleSolver, Stagnation, StagnationResponse, StopCriteria, StopReason,
+    Fabric, FabricGraph, Fanout, Heuristic, Locality, LegacyCostModel, LookaheadTable, PinClass, RipUp, SearchOptions, TimingModel,
+    VprCostModel, Logging, RoutabilityWarning, RoutingConfigBuilder, SimpleSteinerSolver, SteinerSolver, TileManager,
+    SequentialConfig, TestConfig, analyze_routability, create_fasm, create_test, generate_sequential_test, generate_test, route, validate_routing,
+    NetListInternal, SlackReport, TimingAnalysis, route_timing_driven, LutInit, strip_lut_inits,
+    NetExternal, NetListExternal, DEFAULT_ROUTE_THROUGH_COST, SearchWorkspace, CancellationToken,
+};
 use testing_utils::get_test_data_path;
 
 struct MockLogger {
@@ -38,6 +49,44 @@ fn test_create_test_bad_destinations() {
     assert!(result.is_err())
 }
 
+#[test]
+fn test_generate_test_reproducible() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let config = TestConfig {
+        seed: 42,
+        percentage: 0.3,
+        fanout: Fanout::mixed(4),
+        locality: Locality::Rent { exponent: 2.0 },
+        vcc_nets: 2,
+        gnd_nets: 1,
+    };
+    let a = serde_json::to_string(&generate_test(&graph, &config).unwrap()).unwrap();
+    let b = serde_json::to_string(&generate_test(&graph, &config).unwrap()).unwrap();
+    assert_eq!(a, b);
+
+    let other_seed = TestConfig { seed: 43, ..config };
+    let c = serde_json::to_string(&generate_test(&graph, &other_seed).unwrap()).unwrap();
+    assert_ne!(a, c);
+}
+
+#[test]
+fn test_generate_test_max_radius() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let config = TestConfig {
+        seed: 1,
+        fanout: Fanout::Fixed(2),
+        locality: Locality::MaxRadius(1),
+        ..TestConfig::default()
+    };
+    let net_list = generate_test(&graph, &config).unwrap();
+    assert!(!net_list.plan.is_empty());
+    for net in &net_list.plan {
+        for sink in &net.sinks {
+            assert!(net.signal.tile.0.abs_diff(sink.tile.0) + net.signal.tile.1.abs_diff(sink.tile.1) <= 1);
+        }
+    }
+}
+
 #[test]
 fn test_routing_simple() {
     let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
@@ -85,6 +134,175 @@ fn test_routing_steiner() {
     assert!(result.is_ok())
 }
 
+fn route_seeded<R: RouteNet>(solver: R) -> NetListExternal {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 11,
+        percentage: 0.2,
+        fanout: Fanout::Fixed(3),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .solver(solver)
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    routed
+}
+
+#[test]
+fn test_routing_route_tree() {
+    let tree = route_seeded(RouteTreeSolver);
+    let simple = route_seeded(SimpleSolver);
+    let wires = |net_list: &NetListExternal| {
+        net_list
+            .plan
+            .iter()
+            .map(|net| net.result.as_ref().unwrap().nodes.len())
+            .sum::<usize>()
+    };
+    // Sinks share the wires of the tree instead of getting separate paths from the source
+    assert!(wires(&tree) <= wires(&simple));
+    // Some sink branches off a node after the source, i.e. two paths share their first hop
+    let branches_inside = tree.plan.iter().any(|net| {
+        let first_hops = net
+            .result
+            .as_ref()
+            .unwrap()
+            .paths
+            .values()
+            .filter_map(|path| path.get(1))
+            .collect::<Vec<_>>();
+        first_hops.len() > first_hops.iter().collect::<BTreeSet<_>>().len()
+    });
+    assert!(branches_inside);
+}
+
+#[test]
+fn test_find_path_from_tree() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let net_list = create_test(&graph, 0.3, 2).unwrap();
+    let net = &net_list.plan[0];
+    let signal = *graph.get_node_id(&net.signal.id()).unwrap();
+    let sink = *graph.get_node_id(&net.sinks[0].id()).unwrap();
+    let options = SearchOptions::default();
+    let (path, cost) = graph.find_path(signal, sink, &options).unwrap();
+    // Starting from the whole path, the search begins at the node right before the sink
+    let sources = path[..path.len() - 1].iter().map(|node| (*node, 0.0)).collect::<Vec<_>>();
+    let (tree_path, tree_cost) = graph.find_path_from(&sources, sink, &options).unwrap();
+    assert_eq!(tree_path.len(), 2);
+    assert!(tree_cost <= cost);
+    // Costs of a source are added to the path costs
+    let (_, seeded_cost) = graph.find_path_from(&[(signal, 1.0)], sink, &options).unwrap();
+    assert!((seeded_cost - cost - 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_search_workspace_reuse() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let net_list = create_test(&graph, 0.3, 2).unwrap();
+    let options = SearchOptions::default();
+    let mut workspace = SearchWorkspace::new();
+    for net in &net_list.plan {
+        let signal = *graph.get_node_id(&net.signal.id()).unwrap();
+        for sink in &net.sinks {
+            let sink = *graph.get_node_id(&sink.id()).unwrap();
+            // Stale distances of earlier searches must not leak into the next one
+            let reused = graph.find_path_in(&mut workspace, signal, sink, &options);
+            let fresh = graph.find_path_in(&mut SearchWorkspace::new(), signal, sink, &options);
+            assert_eq!(reused, fresh);
+            let backwards = graph.find_path_in(&mut workspace, sink, signal, &options);
+            assert_eq!(backwards, graph.find_path(sink, signal, &options));
+        }
+    }
+}
+
+#[test]
+fn test_dijkstra_all_reuses_workspace() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let (first, second) = (*graph.get_node_id("X1Y1.LA_I0").unwrap(), *graph.get_node_id("X3Y3.LC_I1").unwrap());
+    let costs = graph.dijkstra_all(first);
+    // A search to another node in between must not leave stale costs behind
+    let other = graph.dijkstra_all(second);
+    assert_eq!(graph.dijkstra_all(first), costs);
+    assert_eq!(costs.len(), graph.nodes.len());
+    assert_ne!(costs, other);
+    assert!(costs.iter().any(|cost| *cost < f32::MAX) && costs.contains(&f32::MAX));
+}
+
+#[test]
+fn test_dijkstra_all_charges_like_forward_search() {
+    let mut graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let (start, end) = (*graph.get_node_id("X1Y1.J2MID_ABa_END0").unwrap(), *graph.get_node_id("X1Y1.LA_I0").unwrap());
+    // Different historic costs at both ends tell which of them a path pays for
+    graph.costs[start].historic_cost = 10.0;
+    graph.costs[end].historic_cost = 3.0;
+    let costs = graph.dijkstra_all(end);
+    let (_, cost) = graph.dijkstra(start, end, 0.0).unwrap();
+    assert!((costs[start] - cost).abs() < 1e-3, "{} != {cost}", costs[start]);
+}
+
+/// Reports the first sink of every net as critical once, then meets timing
+#[derive(Default)]
+struct CriticalFirstSink {
+    pub calls: AtomicUsize,
+}
+
+impl TimingAnalysis for CriticalFirstSink {
+    fn timing_analysis(&self, _fabric: &Fabric, net_list: &NetListInternal) -> router::FabricResult<SlackReport> {
+        let criticalities = net_list
+            .plan
+            .iter()
+            .flat_map(|net| net.sinks.iter().enumerate().map(|(i, sink)| ((net.signal, *sink), if i == 0 { 0.95 } else { 0.2 })))
+            .collect::<HashMap<_, _>>();
+        let net = &net_list.plan[0];
+        let worst_slack = if self.calls.fetch_add(1, Ordering::Relaxed) == 0 { -1.0 } else { 1.0 };
+        Ok(SlackReport {
+            slacks: HashMap::new(),
+            criticalities,
+            worst_slack: ((net.signal, net.sinks[0]), worst_slack),
+        })
+    }
+}
+
+fn route_with_criticalities<R: RouteNet>(solver: R) {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 11,
+        percentage: 0.2,
+        fanout: Fanout::Fixed(3),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .solver(solver)
+        .build()
+        .unwrap();
+    let sta = CriticalFirstSink::default();
+    let (routed, iterations) = route_timing_driven(&mut config, &sta).unwrap();
+    assert_eq!(sta.calls.load(Ordering::Relaxed), 2);
+    assert_eq!(iterations.last().unwrap().conflicts, 0);
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+}
+
+#[test]
+fn test_routing_timing_driven_solvers() {
+    route_with_criticalities(SimpleSolver);
+    route_with_criticalities(SteinerSolver);
+    route_with_criticalities(SimpleSteinerSolver);
+    route_with_criticalities(RouteTreeSolver);
+}
+
 #[test]
 fn test_routing_simple_logging() {
     let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
@@ -120,3 +338,991 @@ fn test_create_fasm() {
     let (a, _) = route(&mut config).unwrap();
     let _ = create_fasm(&a, &config.fabric.tile_manager).unwrap();
 }
+
+#[test]
+fn test_diff_net_lists() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .with_test_netlist(0.2, 3)
+        .unwrap()
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    assert!(routed.diff(&routed).is_empty());
+
+    let diff = config.net_list.diff(&routed);
+    assert!(diff.added_nets.is_empty());
+    assert!(diff.removed_nets.is_empty());
+    assert_eq!(diff.old_wire_length, 0);
+    assert!(diff.wire_length_delta() > 0);
+
+    // A sink moved to another input of its LUT is reported as a swap, an unchanged sink is not
+    let graph = &config.fabric.graph;
+    let node = |id: &str| graph.get_node(*graph.get_node_id(id).unwrap()).clone();
+    let signal = node("X1Y1.J2MID_ABa_END0");
+    let net = |sinks: &[&str]| NetExternal {
+        signal: signal.clone(),
+        sinks: sinks.iter().map(|sink| node(sink)).collect(),
+        result: None,
+    };
+    let old = NetListExternal {
+        hash: None,
+        plan: vec![net(&["X1Y1.LA_I0", "X1Y1.LB_I0"])],
+        unroutable: Vec::new(),
+    };
+    let new = NetListExternal {
+        hash: None,
+        plan: vec![net(&["X1Y1.LA_I3", "X1Y1.LB_I0"])],
+        unroutable: Vec::new(),
+    };
+    let swaps = new.swapped_inputs(&old);
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].signal, signal);
+    assert_eq!(swaps[0].sink_old, node("X1Y1.LA_I0"));
+    assert_eq!(swaps[0].sink_new, node("X1Y1.LA_I3"));
+    assert!(old.swapped_inputs(&old).is_empty());
+
+    // Sinks are only paired with a sink of the same LUT, the dropped LA sink has no counterpart
+    let new = NetListExternal {
+        hash: None,
+        plan: vec![net(&["X1Y1.LB_I3"])],
+        unroutable: Vec::new(),
+    };
+    let swaps = old.diff(&new).swaps();
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].sink_old, node("X1Y1.LB_I0"));
+    assert_eq!(swaps[0].sink_new, node("X1Y1.LB_I3"));
+}
+
+fn assert_swaps_stay_on_lut<R: RouteNet>(solver: R) {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let test_config = TestConfig {
+        seed: 11,
+        fanout: Fanout::Fixed(3),
+        ..TestConfig::default()
+    };
+    let net_list = generate_test(&graph, &test_config).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .solver(solver)
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    let swaps = routed.swapped_inputs(&config.net_list);
+    assert!(!swaps.is_empty());
+    // A sink may only move to another input of the same LUT
+    for swap in &swaps {
+        assert_eq!(swap.sink_old.id.split('_').next(), swap.sink_new.id.split('_').next());
+    }
+}
+
+#[test]
+fn test_routing_lut_swaps() {
+    // All solvers share the sink sets and the swap bookkeeping
+    assert_swaps_stay_on_lut(SimpleSolver);
+    assert_swaps_stay_on_lut(SteinerSolver);
+    assert_swaps_stay_on_lut(SimpleSteinerSolver);
+}
+
+#[test]
+fn test_analyze_routability() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut net_list = generate_test(&graph, &TestConfig {
+        seed: 4,
+        percentage: 0.2,
+        fanout: Fanout::Fixed(3),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let fabric = Fabric::new(graph, tile_manager);
+
+    let report = analyze_routability(&fabric, &net_list);
+    assert_eq!(report.net_count, net_list.plan.len());
+    assert_eq!(report.fanout_histogram.get(&3), Some(&net_list.plan.len()));
+    assert!(!report.is_likely_unroutable());
+
+    // The supply of a cut counts every wire crossing it once, not every pip
+    let graph = &fabric.graph;
+    let crossing_wires = |x: u8| {
+        graph
+            .nodes
+            .iter()
+            .zip(graph.map())
+            .filter(|(start, edges)| {
+                edges.iter().any(|edge| {
+                    let end = graph.get_node(edge.node_id);
+                    start.tile.0.min(end.tile.0) <= x && x < start.tile.0.max(end.tile.0)
+                })
+            })
+            .count()
+    };
+    for cut in report.cut_lines.iter().filter(|cut| cut.direction == router::CutDirection::Vertical) {
+        assert!(cut.supply > 0);
+        assert_eq!(cut.supply, crossing_wires(cut.position));
+    }
+
+    let sink = net_list.plan[0].sinks[0].clone();
+    net_list.plan[1].sinks.push(sink);
+    let report = analyze_routability(&fabric, &net_list);
+    assert!(matches!(
+        report.warnings.as_slice(),
+        [RoutabilityWarning::SinkDrivenByMultipleNets { drivers: 2, .. }]
+    ));
+}
+
+#[test]
+fn test_routing_generated_constant_nets() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let config = TestConfig {
+        seed: 3,
+        fanout: Fanout::Fixed(2),
+        vcc_nets: 2,
+        gnd_nets: 2,
+        ..TestConfig::default()
+    };
+    let net_list = generate_test(&graph, &config).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .build()
+        .unwrap();
+    assert!(route(&mut config).is_ok());
+}
+
+#[test]
+fn test_routing_fold_constants() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let test_config = TestConfig {
+        seed: 3,
+        fanout: Fanout::Fixed(2),
+        vcc_nets: 2,
+        gnd_nets: 2,
+        ..TestConfig::default()
+    };
+    let net_list = generate_test(&graph, &test_config).unwrap();
+    // Every LUT with a sink computes the AND of its four inputs
+    let luts = net_list
+        .plan
+        .iter()
+        .flat_map(|net| &net.sinks)
+        .map(|sink| (sink.tile, sink.id.chars().nth(1).unwrap()))
+        .collect::<BTreeSet<_>>();
+    let inits = luts
+        .iter()
+        .map(|(tile, bel_index)| LutInit::new(0x8000, 4).to_fasm(*tile, *bel_index))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .fold_constants(true)
+        .build()
+        .unwrap();
+    config.fabric.tile_manager.load_inits(&inits).unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    assert!(routed.plan.iter().all(|net| net.signal.id != "VCC0" && net.signal.id != "GND0"));
+
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    for (tile, bel_index) in &luts {
+        let constant_inputs = |constant: &str| {
+            config
+                .net_list
+                .plan
+                .iter()
+                .filter(|net| net.signal.id == constant)
+                .flat_map(|net| &net.sinks)
+                .filter(|sink| sink.tile == *tile && sink.id.chars().nth(1) == Some(*bel_index))
+                .count()
+        };
+        let (vcc, gnd) = (constant_inputs("VCC0"), constant_inputs("GND0"));
+        let prefix = format!("{tile}.{bel_index}.INIT[15:0] = 16'b");
+        let bits = fasm.lines().find_map(|line| line.strip_prefix(&prefix)).unwrap();
+        // Tying an input of an AND to 0 clears it, tying it to 1 doubles the ones
+        let ones = if gnd > 0 { 0 } else { 1 << vcc };
+        assert_eq!(bits.chars().filter(|bit| *bit == '1').count(), ones);
+    }
+}
+
+#[test]
+fn test_routing_constant_drivers() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let test_config = TestConfig {
+        seed: 3,
+        fanout: Fanout::Fixed(2),
+        vcc_nets: 2,
+        gnd_nets: 2,
+        ..TestConfig::default()
+    };
+    let net_list = generate_test(&graph, &test_config).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .constant_search_radius(1)
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    let tie_offs = fasm
+        .lines()
+        .filter(|line| line.ends_with(&format!("16'b{}", "0".repeat(16))) || line.ends_with(&format!("16'b{}", "1".repeat(16))))
+        .count();
+    assert!(tie_offs > 0);
+    // Tie-offs drive every constant sink the dedicated wires cannot reach and are shared between sinks
+    let lut_driven = routed
+        .plan
+        .iter()
+        .filter(|net| net.signal.id != "VCC0" && net.signal.id != "GND0")
+        .count();
+    assert!(tie_offs <= lut_driven);
+}
+
+/// Routes a `VCC` net to a sink the `VCC0` wires cannot reach, with the truth tables of `inits` loaded.
+/// Returns the FASM and the LUT of the tie-off, e.g. `X2Y2.A`.
+fn route_constant_tie_off(inits: &str) -> (String, String) {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let node = |id: &str| graph.get_node(*graph.get_node_id(id).unwrap()).clone();
+    let net_list = NetListExternal {
+        hash: None,
+        plan: vec![NetExternal {
+            signal: node("X1Y1.VCC0"),
+            sinks: vec![node("X2Y2.LC_I2")],
+            result: None,
+        }],
+        unroutable: Vec::new(),
+    };
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .constant_search_radius(1)
+        .build()
+        .unwrap();
+    config.fabric.tile_manager.load_inits(inits).unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    let tie_off = fasm
+        .lines()
+        .find(|line| line.ends_with(&format!("16'b{}", "1".repeat(16))))
+        .and_then(|line| line.split_once(".INIT"))
+        .map(|(lut, _)| lut.to_string())
+        .unwrap();
+    (fasm, tie_off)
+}
+
+#[test]
+fn test_routing_constant_skips_luts_with_init() {
+    let (_, tie_off) = route_constant_tie_off("");
+    // The LUT of the design only feeds its FF, so it has no net but a truth table
+    let init = format!("{tie_off}.INIT[15:0] = 16'h8000");
+    let (fasm, other) = route_constant_tie_off(&init);
+    assert_ne!(other, tie_off);
+    assert_eq!(fasm.lines().filter(|line| line.starts_with(&format!("{tie_off}.INIT"))).count(), 1);
+    assert_eq!(fasm.lines().filter(|line| line.starts_with(&format!("{other}.INIT"))).count(), 1);
+}
+
+#[test]
+fn test_routing_lut_route_through() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    // J2MID_ABa_END0 only drives the inputs of the free LUTs A and B
+    let node = |id: &str| graph.get_node(*graph.get_node_id(id).unwrap()).clone();
+    let net_list = NetListExternal {
+        hash: None,
+        plan: vec![NetExternal {
+            signal: node("X1Y1.J2MID_ABa_END0"),
+            sinks: vec![node("X3Y3.LC_I1")],
+            result: None,
+        }],
+        unroutable: Vec::new(),
+    };
+    let mut unroutable = RoutingConfigBuilder::default()
+        .graph(graph.clone())
+        .tile_manager(TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap())
+        .net_list(net_list.clone())
+        .build()
+        .unwrap();
+    assert!(route(&mut unroutable).is_err());
+
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .lut_route_through_cost(Some(DEFAULT_ROUTE_THROUGH_COST))
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    let path = &routed.plan[0].result.as_ref().unwrap().paths.values().next().unwrap();
+    let (input, output) = (&path[1], &path[2]);
+    assert_eq!(output.id, format!("L{}_O", &input.id[1..2]));
+
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    let bel_index = input.id.chars().nth(1).unwrap();
+    let pin = input.id[4..].parse::<u8>().unwrap();
+    assert!(fasm.lines().any(|line| line == LutInit::identity(4, pin).to_fasm(input.tile, bel_index)));
+    assert!(!fasm.contains(&format!("{}.{}", input.id, output.id)));
+}
+
+#[test]
+fn test_routing_partial_route_through() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let node = |id: &str| graph.get_node(*graph.get_node_id(id).unwrap()).clone();
+    // Five nets end on the four inputs of LUT C, so the only iteration ends with a conflict
+    let net = |signal: &str, sink: &str| NetExternal {
+        signal: node(signal),
+        sinks: vec![node(sink)],
+        result: None,
+    };
+    let net_list = NetListExternal {
+        hash: None,
+        plan: vec![
+            net("X1Y1.J2MID_ABa_END0", "X3Y3.LC_I1"),
+            net("X3Y3.J2MID_CDa_END0", "X3Y3.LC_I0"),
+            net("X3Y3.J2MID_CDa_END1", "X3Y3.LC_I1"),
+            net("X3Y3.J2MID_CDa_END2", "X3Y3.LC_I2"),
+            net("X3Y3.J2MID_CDa_END3", "X3Y3.LC_I3"),
+        ],
+        unroutable: Vec::new(),
+    };
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .lut_route_through_cost(Some(DEFAULT_ROUTE_THROUGH_COST))
+        .max_iterations(0)
+        .build()
+        .unwrap();
+    // A failed run borrows the LUTs its best routing passes through as well
+    let result = route(&mut config);
+    let Err(FabricError::RoutingMaxIterationsReached { best_routing, .. }) = result else {
+        panic!("expected the routing to reach max_iterations, got {result:?}");
+    };
+    let best_routing = best_routing.unwrap();
+    let path = best_routing.net_list.plan[0].result.as_ref().unwrap().paths.values().next().unwrap().clone();
+    let input = &path[1];
+    let fasm = best_routing.to_fasm(&config.fabric.tile_manager).unwrap();
+    let pin = input.id[4..].parse::<u8>().unwrap();
+    assert!(fasm.lines().any(|line| line == LutInit::identity(4, pin).to_fasm(input.tile, input.id.chars().nth(1).unwrap())));
+}
+
+#[test]
+fn test_routing_skip_unroutable() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    // Without route-throughs J2MID_ABa_END0 only reaches the inputs of its own LUTs A and B
+    let node = |id: &str| graph.get_node(*graph.get_node_id(id).unwrap()).clone();
+    let net_list = NetListExternal {
+        hash: None,
+        plan: vec![NetExternal {
+            signal: node("X1Y1.J2MID_ABa_END0"),
+            sinks: vec![node("X1Y1.LA_I0"), node("X3Y3.LC_I1")],
+            result: None,
+        }],
+        unroutable: Vec::new(),
+    };
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .skip_unroutable(true)
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+    assert_eq!(routed.unroutable.len(), 1);
+    assert_eq!(routed.unroutable[0].signal.id, "J2MID_ABa_END0");
+    assert_eq!(routed.unroutable[0].sink.id, "LC_I1");
+    assert!(!routed.unroutable[0].reason.is_empty());
+    let result = routed.plan[0].result.as_ref().unwrap();
+    assert_eq!(result.paths.keys().map(|sink| sink.id.as_str()).collect::<Vec<_>>(), vec!["LA_I0"]);
+    assert!(routed.swapped_inputs(&config.net_list).is_empty());
+}
+
+#[test]
+fn test_routing_sequential_test() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let config = SequentialConfig {
+        seed: 7,
+        chains: 3,
+        logic_depth: 2,
+        locality: Locality::MaxRadius(2),
+    };
+    let test = generate_sequential_test(&graph, &config).unwrap();
+    assert_eq!(test.registers.len(), 6);
+    assert_eq!(test.net_list.plan.len(), 9);
+    assert_eq!(test.inits.len(), 9);
+    let fasm = test.fasm();
+    assert_eq!(fasm.lines().filter(|line| line.ends_with(".FF")).count(), 6);
+    assert_eq!(fasm.lines().filter(|line| line.contains(".INIT[15:0] = 16'b")).count(), 9);
+
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(test.net_list)
+        .build()
+        .unwrap();
+    assert!(route(&mut config).is_ok());
+}
+
+#[test]
+fn test_create_fasm_permutes_inits() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let sequential_config = SequentialConfig {
+        seed: 7,
+        chains: 3,
+        logic_depth: 2,
+        locality: Locality::MaxRadius(2),
+    };
+    let test = generate_sequential_test(&graph, &sequential_config).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(test.net_list.clone())
+        .build()
+        .unwrap();
+    assert_eq!(config.fabric.tile_manager.load_inits(&test.fasm()).unwrap(), 9);
+    let (routed, _) = route(&mut config).unwrap();
+    assert!(!routed.swapped_inputs(&config.net_list).is_empty());
+
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    assert_eq!(fasm.lines().filter(|line| line.contains(".INIT[15:0]")).count(), 9);
+    // Every LUT forwards its only input, so the INIT has to follow the routed pin
+    for sink in routed.plan.iter().flat_map(|net| &net.sinks) {
+        let bel_index = sink.id.chars().nth(1).unwrap();
+        let input = sink.id.chars().last().unwrap().to_digit(10).unwrap();
+        let init = LutInit::identity(4, u8::try_from(input).unwrap());
+        assert!(fasm.lines().any(|line| line == init.to_fasm(sink.tile, bel_index)));
+    }
+    assert_eq!(strip_lut_inits(&test.fasm()).lines().count(), 6);
+}
+
+#[test]
+fn test_astar_matches_dijkstra() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    assert!(graph.max_edge_span > 0);
+    let net_list = create_test(&graph, 0.3, 2).unwrap();
+    for net in &net_list.plan {
+        let signal = *graph.get_node_id(&net.signal.id()).unwrap();
+        for sink in &net.sinks {
+            let sink = *graph.get_node_id(&sink.id()).unwrap();
+            for criticality in [0.0, 0.5, 1.0] {
+                let dijkstra = graph.dijkstra(signal, sink, criticality).map(|(_, cost)| cost);
+                let astar = graph.astar(signal, sink, criticality).map(|(_, cost)| cost);
+                match (dijkstra, astar) {
+                    (Some(a), Some(b)) => assert!((a - b).abs() < 1e-4, "{a} != {b}"),
+                    (a, b) => assert_eq!(a.is_some(), b.is_some()),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_routing_astar() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .heuristic(Heuristic::Manhattan)
+        .with_test_netlist(0.2, 3)
+        .unwrap()
+        .solver(SteinerSolver)
+        .build()
+        .unwrap();
+    assert!(route(&mut config).is_ok());
+}
+
+#[test]
+fn test_lookahead_table() {
+    let mut graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let table = LookaheadTable::compute(&graph);
+    let net_list = create_test(&graph, 0.3, 2).unwrap();
+
+    let json = serde_json::to_string(&table).unwrap();
+    let restored: LookaheadTable = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.hash, graph.calculate_structure_hash());
+    graph.set_lookahead(restored).unwrap();
+
+    let mut entries = 0;
+    for net in &net_list.plan {
+        let signal = *graph.get_node_id(&net.signal.id()).unwrap();
+        for sink in &net.sinks {
+            let sink_id = *graph.get_node_id(&sink.id()).unwrap();
+            if table.get(router::wire_type(&net.signal), net.signal.tile, sink.tile, PinClass::LutInput).is_some() {
+                entries += 1;
+            }
+            let dijkstra = graph.dijkstra(signal, sink_id, 0.0).map(|(_, cost)| cost);
+            let lookahead = graph
+                .find_path(signal, sink_id, &SearchOptions {
+                    heuristic: Heuristic::Lookahead,
+                    ..SearchOptions::default()
+                })
+                .map(|(_, cost)| cost);
+            assert_eq!(dijkstra.is_some(), lookahead.is_some());
+        }
+    }
+    assert!(entries > 0);
+
+    let other = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), Some(TimingModel {
+        pip_delay: 2.0,
+        ..TimingModel::default()
+    }))
+    .unwrap();
+    assert!(other.clone().set_lookahead(table).is_err());
+}
+
+#[test]
+fn test_routing_lookahead() {
+    let mut graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    graph.set_lookahead(LookaheadTable::compute(&graph)).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .heuristic(Heuristic::Lookahead)
+        .with_test_netlist(0.2, 3)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(route(&mut config).is_ok());
+}
+
+#[test]
+fn test_search_bounding_box() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let net_list = create_test(&graph, 0.3, 1).unwrap();
+    for net in &net_list.plan {
+        let signal = *graph.get_node_id(&net.signal.id()).unwrap();
+        let sink = *graph.get_node_id(&net.sinks[0].id()).unwrap();
+        let bounding_box = BoundingBox::from_tiles([net.signal.tile, net.sinks[0].tile]).unwrap();
+        let options = SearchOptions {
+            bounding_box: Some(bounding_box.expand(1, &graph.extent().unwrap())),
+            ..SearchOptions::default()
+        };
+        if let Some((path, cost)) = graph.find_path(signal, sink, &options) {
+            assert!(path.iter().all(|node| options.bounding_box.unwrap().contains(graph.get_node(*node).tile)));
+            assert!(cost >= graph.dijkstra(signal, sink, 0.0).unwrap().1);
+        }
+    }
+}
+
+#[test]
+fn test_routing_bounding_box() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .bounding_box_margin(0)
+        .with_test_netlist(0.2, 3)
+        .unwrap()
+        .build()
+        .unwrap();
+    let (_, iterations) = route(&mut config).unwrap();
+    assert_eq!(iterations[0].test_case.bounding_box_margin, Some(0));
+}
+
+#[test]
+fn test_routing_incremental_rip_up() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 5,
+        percentage: 0.5,
+        fanout: Fanout::Fixed(2),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .rip_up(RipUp::Congested)
+        .net_list(net_list)
+        .build()
+        .unwrap();
+    let (routed, iterations) = route(&mut config).unwrap();
+    assert_eq!(iterations.last().unwrap().conflicts, 0);
+    assert_eq!(iterations[0].test_case.rip_up, RipUp::Congested);
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+}
+
+fn route_with_cost_model<C: CostModel + 'static>(cost_model: C) {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .cost_model(cost_model)
+        .with_test_netlist(0.3, 2)
+        .unwrap()
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+}
+
+#[test]
+fn test_routing_cost_models() {
+    route_with_cost_model(LegacyCostModel);
+    route_with_cost_model(VprCostModel {
+        initial_present_factor: 1.0,
+        present_factor_growth: 2.0,
+        max_present_factor: 100.0,
+    });
+}
+
+#[test]
+fn test_routing_parallel_nets() {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 11,
+        percentage: 0.4,
+        fanout: Fanout::Fixed(2),
+        locality: Locality::MaxRadius(1),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .bounding_box_margin(1)
+        .parallel_nets(true)
+        .net_list(net_list)
+        .build()
+        .unwrap();
+    let (routed, iterations) = route(&mut config).unwrap();
+    assert_eq!(iterations.last().unwrap().conflicts, 0);
+    assert!(iterations[0].test_case.parallel_nets);
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+}
+
+fn route_to_bytes<R: RouteNet>(solver: R) -> (String, String) {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 21,
+        percentage: 0.3,
+        fanout: Fanout::Fixed(3),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    let mut config = RoutingConfigBuilder::default()
+        .graph(graph)
+        .tile_manager(tile_manager)
+        .net_list(net_list)
+        .solver(solver)
+        .build()
+        .unwrap();
+    let (routed, _) = route(&mut config).unwrap();
+    let fasm = create_fasm(&routed, &config.fabric.tile_manager).unwrap();
+    (serde_json::to_string(&routed).unwrap(), fasm)
+}
+
+fn route_in_pool<R: RouteNet + Send>(threads: usize, solver: R) -> (String, String) {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    pool.install(|| route_to_bytes(solver))
+}
+
+#[test]
+fn test_routing_is_reproducible() {
+    assert_eq!(route_to_bytes(SimpleSolver), route_to_bytes(SimpleSolver));
+    assert_eq!(route_to_bytes(SteinerSolver), route_to_bytes(SteinerSolver));
+    assert_eq!(route_in_pool(1, SimpleSolver), route_in_pool(4, SimpleSolver));
+    assert_eq!(route_in_pool(1, SteinerSolver), route_in_pool(4, SteinerSolver));
+}
+
+#[test]
+fn test_routing_net_orderings() {
+    let orderings = [
+        NetOrdering::AsGiven,
+        NetOrdering::Fanout,
+        NetOrdering::BoundingBox,
+        NetOrdering::Criticality,
+        NetOrdering::Congestion,
+        NetOrdering::Random { seed: 4 },
+    ];
+    for net_ordering in orderings {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let net_list = generate_test(&graph, &TestConfig {
+            seed: 8,
+            percentage: 0.3,
+            fanout: Fanout::mixed(4),
+            ..TestConfig::default()
+        })
+        .unwrap();
+        let mut config = RoutingConfigBuilder::default()
+            .graph(graph)
+            .tile_manager(tile_manager)
+            .net_ordering(net_ordering)
+            .net_list(net_list)
+            .build()
+            .unwrap();
+        let (routed, iterations) = route(&mut config).unwrap();
+        assert!(iterations.iter().all(|iteration| iteration.test_case.net_ordering == net_ordering));
+        validate_routing(&config.fabric.graph, &routed).unwrap();
+    }
+}
+
+fn congested_config() -> RoutingConfigBuilder<SimpleSolver, router::SimpleLogging> {
+    let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+    let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    let net_list = generate_test(&graph, &TestConfig {
+        seed: 5,
+        percentage: 0.5,
+        fanout: Fanout::Fixed(2),
+        ..TestConfig::default()
+    })
+    .unwrap();
+    RoutingConfigBuilder::default().graph(graph).tile_manager(tile_manager).net_list(net_list)
+}
+
+#[test]
+fn test_routing_stop_criteria() {
+    let mut config = congested_config()
+        .stop_criteria(StopCriteria {
+            no_improvement_window: Some(0),
+            ..StopCriteria::default()
+        })
+        .build()
+        .unwrap();
+    match route(&mut config) {
+        Err(FabricError::RoutingStopped {
+            reason, iteration_report, ..
+        }) => {
+            assert_eq!(reason, StopReason::NoImprovement);
+            assert_eq!(iteration_report.len(), 1);
+        }
+        other => panic!("expected the missing improvement to stop the routing, got {other:?}"),
+    }
+}
+
+/// Cancels the routing once the first iteration is logged
+struct CancellingLogger(CancellationToken);
+impl Logging for CancellingLogger {
+    fn log(&self, log_instance: &router::LogInstance) -> router::FabricResult<()> {
+        if matches!(log_instance, router::LogInstance::RouterIteration(_)) {
+            self.0.cancel();
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_routing_cancellation() {
+    let token = CancellationToken::new();
+    let mut config = congested_config()
+        .cancellation(token.clone())
+        .logger(CancellingLogger(token))
+        .build()
+        .unwrap();
+    let net_count = config.net_list.plan.len();
+    match route(&mut config) {
+        Err(FabricError::RoutingInterrupted {
+            reason,
+            best_routing,
+            iteration_report,
+        }) => {
+            assert_eq!(reason, StopReason::Cancelled);
+            assert_eq!(iteration_report.len(), 1);
+            assert!(iteration_report[0].conflicts > 0);
+            let best_routing = best_routing.unwrap();
+            assert!(best_routing.net_list.plan.len() >= net_count);
+            assert!(best_routing.net_list.plan.iter().all(|net| net.result.is_some()));
+        }
+        other => panic!("expected the cancellation to interrupt the routing, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_routing_partial_result() {
+    let mut config = congested_config().max_iterations(0).build().unwrap();
+    let net_count = config.net_list.plan.len();
+    // Every LUT with a sink forwards the first of its placed inputs, so its INIT follows that pin
+    let mut forwarded = BTreeMap::new();
+    for sink in config.net_list.plan.iter().flat_map(|net| &net.sinks) {
+        forwarded.entry((sink.tile, sink.id.chars().nth(1).unwrap())).or_insert_with(|| sink.clone());
+    }
+    let pin = |input: &str| u8::try_from(input.chars().last().unwrap().to_digit(10).unwrap()).unwrap();
+    let inits = forwarded
+        .iter()
+        .map(|((tile, bel_index), sink)| LutInit::identity(4, pin(&sink.id)).to_fasm(*tile, *bel_index))
+        .collect::<Vec<String>>()
+        .join("\n");
+    config.fabric.tile_manager.load_inits(&inits).unwrap();
+    match route(&mut config) {
+        Err(FabricError::RoutingMaxIterationsReached {
+            iteration_report,
+            best_routing,
+            ..
+        }) => {
+            let best_routing = best_routing.unwrap();
+            assert!(best_routing.net_list.plan.len() >= net_count);
+            assert!(best_routing.net_list.plan.iter().all(|net| net.result.is_some()));
+            assert_eq!(best_routing.conflicts.len(), iteration_report[0].conflicts);
+            assert!(best_routing.conflicts.values().all(|signals| signals.len() >= 2));
+            let fasm = best_routing.to_fasm(&config.fabric.tile_manager).unwrap();
+            assert_eq!(fasm.lines().filter(|line| line.starts_with("# Conflict")).count(), best_routing.conflicts.len());
+
+            // The tile manager of the failed run carries the permutations of the partial routing
+            let swaps = best_routing.net_list.swapped_inputs(&config.net_list);
+            assert!(swaps.iter().any(|swap| forwarded.values().any(|sink| *sink == swap.sink_old)));
+            for ((tile, bel_index), sink) in &forwarded {
+                let routed = swaps.iter().find(|swap| swap.sink_old == *sink).map_or(sink, |swap| &swap.sink_new);
+                let init = LutInit::identity(4, pin(&routed.id)).to_fasm(*tile, *bel_index);
+                assert!(fasm.lines().any(|line| line == init), "{init} missing from the partial FASM");
+            }
+        }
+        other => panic!("expected the routing to reach max_iterations, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_routing_keep_best_routing() {
+    for keep in [false, true] {
+        let graph = FabricGraph::from_file(&get_test_data_path("pips_4x4.txt"), None).unwrap();
+        let tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+        let net_list = generate_test(&graph, &TestConfig {
+            seed: 3,
+            percentage: 0.8,
+            fanout: Fanout::Fixed(4),
+            ..TestConfig::default()
+        })
+        .unwrap();
+        let mut config = RoutingConfigBuilder::default()
+            .graph(graph)
+            .tile_manager(tile_manager)
+            .net_list(net_list)
+            .max_iterations(4)
+            .keep_best_routing(keep)
+            .build()
+            .unwrap();
+        match route(&mut config) {
+            Err(FabricError::RoutingMaxIterationsReached {
+                congestion_report,
+                iteration_report,
+                best_routing,
+            }) => {
+                let best_routing = best_routing.unwrap();
+                // Every iteration is reported, including the last one
+                assert_eq!(iteration_report.len(), 5);
+                let fewest = iteration_report.iter().map(|result| result.conflicts).min().unwrap();
+                if keep {
+                    assert_eq!(best_routing.conflicts.len(), fewest);
+                } else {
+                    // The routing of the last iteration, the one of the congestion report
+                    assert!(best_routing.conflicts.keys().eq(congestion_report.congestion.keys().collect::<BTreeSet<_>>()));
+                }
+            }
+            other => panic!("expected the routing to reach max_iterations, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_routing_time_limit() {
+    let mut config = congested_config()
+        .stop_criteria(StopCriteria {
+            time_limit: Some(Duration::ZERO),
+            ..StopCriteria::default()
+        })
+        .build()
+        .unwrap();
+    match route(&mut config) {
+        Err(FabricError::RoutingInterrupted {
+            reason,
+            best_routing,
+            iteration_report,
+        }) => {
+            assert_eq!(reason, StopReason::TimeLimit);
+            assert!(best_routing.is_none());
+            assert!(iteration_report.is_empty());
+        }
+        other => panic!("expected the time limit to interrupt the routing, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_routing_stagnation_responses() {
+    let mut config = congested_config()
+        .stagnation(Stagnation {
+            window: 1,
+            responses: vec![
+                StagnationResponse::PreProcess,
+                StagnationResponse::HistoricBoost(2.0),
+                StagnationResponse::RandomReorder { seed: 1 },
+                StagnationResponse::WidenBoundingBoxes(1),
+            ],
+        })
+        .bounding_box_margin(0)
+        .build()
+        .unwrap();
+    let (routed, iterations) = route(&mut config).unwrap();
+    assert_eq!(iterations.last().unwrap().conflicts, 0);
+    validate_routing(&config.fabric.graph, &routed).unwrap();
+}
+
+#[test]
+fn test_widen_bounding_boxes_requires_margin() {
+    let result = congested_config()
+        .stagnation(Stagnation {
+            window: 1,
+            responses: vec![StagnationResponse::WidenBoundingBoxes(1)],
+        })
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_historic_boost_requires_factor_of_at_least_one() {
+    let boost = |factor: f32| {
+        congested_config()
+            .stagnation(Stagnation {
+                window: 1,
+                responses: vec![StagnationResponse::HistoricBoost(factor)],
+            })
+            .build()
+            .is_ok()
+    };
+    assert!(boost(1.0));
+    assert!(boost(2.0));
+    for factor in [0.5, 0.0, -1.0, f32::NAN, f32::INFINITY] {
+        assert!(!boost(factor), "{factor} was accepted");
+    }
+}
+
+#[test]
+fn test_load_inits_rejects_wide_lut() {
+    let mut tile_manager = TileManager::from_file(&get_test_data_path("bel_4x4.txt")).unwrap();
+    assert_eq!(tile_manager.load_inits("X1Y1.A.INIT[15:0] = 16'h8000").unwrap(), 1);
+    // A 6 input INIT does not fit the 4 pins of the LUT and must not reach the permutation
+    assert!(matches!(
+        tile_manager.load_inits("X1Y1.A.INIT[63:0] = 64'h1"),
+        Err(FabricError::LutInitTooWide { inputs: 6, pins: 4, .. })
+    ));
+    assert_eq!(tile_manager.generate_init_fasm().len(), 1);
+}
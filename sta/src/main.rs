@@ -69,7 +69,7 @@ fn main() {
     for (model_name, timing_model) in &timing_models {
         log::info!("Loading Timing Model: {}", model_name);
         
-        let design = build_design(&pips, &configurations, &flops, &timing_model);
+        let design = build_design(&pips, &configurations, &flops, timing_model);
         
         let json_string = serde_json::to_string_pretty(&design_to_json_map(&design)).expect("Failed to serialize design");
         std::fs::write("output/design.json", json_string).expect("Failed to write design to file");
@@ -42,11 +42,13 @@ pub fn pips_parser(file_path: &str) -> Vec<Pip> {
     pips
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn fasm_parser(file_path: &str) -> Result<(Vec<Configuration>, Vec<Flop>), ()> {
     let fasm_file_content = fs::read_to_string(file_path).unwrap_or_else(|_| "".to_string());
     fasm_parser_string(&fasm_file_content)
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn fasm_parser_string(content: &str) -> Result<(Vec<Configuration>, Vec<Flop>), ()> {
     let mut configurations: Vec<Configuration> = Vec::new();
     let mut flops: Vec<Flop> = Vec::new();